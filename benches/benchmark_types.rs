@@ -5,6 +5,7 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use hulc2envolventecte::{
     collect_hulc_data,
+    cte::{IdScheme, Model, WindowUMethod},
     parsers::{ctehexml, kyg, tbl},
 };
 
@@ -12,14 +13,28 @@ fn load_caso_a() {
     let ctehexmlpath = ctehexml::find_ctehexml("tests/casoA").unwrap();
     let kygpath = kyg::find_kyg("tests/casoA").unwrap();
     let tblpath = tbl::find_tbl("tests/casoA").unwrap();
-    let _data = collect_hulc_data(ctehexmlpath, kygpath, tblpath).unwrap();
+    let _data = collect_hulc_data(
+        ctehexmlpath,
+        kygpath,
+        tblpath,
+        WindowUMethod::default(),
+        IdScheme::default(),
+    )
+    .unwrap();
 }
 
 fn load_caso_c() {
     let ctehexmlpath = ctehexml::find_ctehexml("tests/casoC").unwrap();
     let kygpath = kyg::find_kyg("tests/casoC").unwrap();
     let tblpath = tbl::find_tbl("tests/casoC").unwrap();
-    let _data = collect_hulc_data(ctehexmlpath, kygpath, tblpath).unwrap();
+    let _data = collect_hulc_data(
+        ctehexmlpath,
+        kygpath,
+        tblpath,
+        WindowUMethod::default(),
+        IdScheme::default(),
+    )
+    .unwrap();
 }
 
 // Caso más antiguo con archivo generado con el HULC2018 que salió a información pública
@@ -27,13 +42,74 @@ fn load_test_data() {
     let ctehexmlpath = ctehexml::find_ctehexml("tests/data").unwrap();
     let kygpath = kyg::find_kyg("tests/data").unwrap();
     let tblpath = tbl::find_tbl("tests/data").unwrap();
-    let _data = collect_hulc_data(ctehexmlpath, kygpath, tblpath).unwrap();
+    let _data = collect_hulc_data(
+        ctehexmlpath,
+        kygpath,
+        tblpath,
+        WindowUMethod::default(),
+        IdScheme::default(),
+    )
+    .unwrap();
+}
+
+// Indicadores calculados sobre un modelo ya cargado (búsquedas indexadas de elementos)
+fn model_caso_c() -> Model {
+    let ctehexmlpath = ctehexml::find_ctehexml("tests/casoC").unwrap();
+    collect_hulc_data(
+        ctehexmlpath,
+        None,
+        None,
+        WindowUMethod::default(),
+        IdScheme::default(),
+    )
+    .unwrap()
+}
+
+// Modelo ampliado con n copias de los espacios, opacos y huecos, para proyectos grandes
+fn enlarge(model: &Model, n: usize) -> Model {
+    let mut big = model.clone();
+    for i in 1..n {
+        let sfx = |id: &str| format!("{}-{}", id, i);
+        big.spaces.extend(model.spaces.iter().map(|s| {
+            let mut s = s.clone();
+            s.id = sfx(&s.id);
+            s
+        }));
+        big.walls.extend(model.walls.iter().map(|w| {
+            let mut w = w.clone();
+            w.id = sfx(&w.id);
+            w.space = sfx(&w.space);
+            w.nextto = w.nextto.as_deref().map(sfx);
+            w
+        }));
+        big.windows.extend(model.windows.iter().map(|w| {
+            let mut w = w.clone();
+            w.id = sfx(&w.id);
+            w.wall = sfx(&w.wall);
+            w
+        }));
+    }
+    big
+}
+
+fn indicators(model: &Model) {
+    let _u_values = model.u_values();
+    let _k = model.K_he2019();
+    let _compacity = model.compacity();
+    let _q_soljul = model.q_soljul();
+    let _n50 = model.n50_he2019();
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("caso A", |b| b.iter(|| load_caso_a()));
     c.bench_function("caso C", |b| b.iter(|| load_caso_c()));
     c.bench_function("test_data", |b| b.iter(|| load_test_data()));
+    let model = model_caso_c();
+    c.bench_function("indicadores caso C", |b| b.iter(|| indicators(&model)));
+    c.bench_function("índices caso C", |b| b.iter(|| model.index()));
+    let big = enlarge(&model, 50);
+    c.bench_function("indicadores caso C x50", |b| b.iter(|| indicators(&big)));
+    c.bench_function("H_ve caso C x50", |b| b.iter(|| big.H_ve()));
 }
 
 criterion_group!(benches, criterion_benchmark);
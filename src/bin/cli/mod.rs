@@ -5,20 +5,27 @@
 
 use std::process::exit;
 
-use anyhow::Result;
+use anyhow::{format_err, Result};
 
 use hulc2envolventecte::{
-    collect_hulc_data, get_copytxt,
+    collect_hulc_data_from_ctehexml,
+    cte::{batch_results_to_csv, BatchDefinition, IdScheme, WindowUMethod},
+    get_copytxt,
     parsers::{ctehexml, kyg, tbl},
+    utils::read_file,
     PROGNAME,
 };
 
 fn get_help() -> String {
     format!(
-        "Uso: {} [--skip-kyg] DIRECTORIO
+        "Uso: {} [--skip-kyg] [--legacy-ids] [--window-u-iso10077 PSI_G] [--batch ESCENARIOS] DIRECTORIO
 
 Opciones:
 --skip-kyg      Ignorar datos obtenidos del archivo KyGananciasSolares.txt
+--legacy-ids    Generar los id de los elementos con el método de versiones anteriores
+--window-u-iso10077 PSI_G  Calcular la U de los huecos según UNE-EN ISO 10077-1, con la transmitancia
+                lineal del intercalario PSI_G (W/mK), en lugar de con el método de HULC
+--batch ESCENARIOS  Evaluar las combinaciones del archivo JSON de escenarios y emitir los resultados en CSV
 
 Argumentos:
 DIRECTORIO     Directorio del proyecto de HULC
@@ -34,15 +41,21 @@ Puede redirigir la salida de resultados a un archivo para su uso posterior:
     )
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 struct Options {
     use_extra_files: bool,
+    legacy_ids: bool,
+    window_u_method: WindowUMethod,
+    batch: Option<String>,
 }
 
 impl Default for Options {
     fn default() -> Self {
         Self {
             use_extra_files: true,
+            legacy_ids: false,
+            window_u_method: WindowUMethod::default(),
+            batch: None,
         }
     }
 }
@@ -65,7 +78,8 @@ pub fn cli_main() -> Result<()> {
         // Opciones + directorio de proyecto
         _ => {
             let mut opts = Options::default();
-            for opt in &args[1..args.len() - 1] {
+            let mut opt_args = args[1..args.len() - 1].iter();
+            while let Some(opt) = opt_args.next() {
                 match opt.as_ref() {
                     "--skip-extra" => {
                         eprintln!(
@@ -73,6 +87,38 @@ pub fn cli_main() -> Result<()> {
                         );
                         opts.use_extra_files = false;
                     }
+                    "--legacy-ids" => {
+                        eprintln!("Se generarán los id con el método de versiones anteriores");
+                        opts.legacy_ids = true;
+                    }
+                    "--window-u-iso10077" => {
+                        match opt_args.next().map(|psi_g| psi_g.parse::<f32>()) {
+                            Some(Ok(psi_g)) if psi_g >= 0.0 => {
+                                eprintln!(
+                                    "Se calculará la U de los huecos según UNE-EN ISO 10077-1 (psi_g={} W/mK)",
+                                    psi_g
+                                );
+                                opts.window_u_method = WindowUMethod::ISO10077 { psi_g };
+                            }
+                            _ => {
+                                eprintln!(
+                                    "Falta el valor de PSI_G, o no es válido, en la opción --window-u-iso10077\n\n{}",
+                                    get_help()
+                                );
+                                exit(1)
+                            }
+                        }
+                    }
+                    "--batch" => match opt_args.next() {
+                        Some(batchpath) => opts.batch = Some(batchpath.clone()),
+                        None => {
+                            eprintln!(
+                                "Falta el archivo de escenarios de la opción --batch\n\n{}",
+                                get_help()
+                            );
+                            exit(1)
+                        }
+                    },
                     _ => (),
                 }
             }
@@ -80,6 +126,12 @@ pub fn cli_main() -> Result<()> {
         }
     };
 
+    // Falta el directorio de proyecto (p.e. la última opción ocupa su lugar)
+    if dir.starts_with("--") {
+        eprintln!("{}", get_help());
+        exit(1)
+    }
+
     // Localiza archivos
     eprintln!("Localizando archivos de datos en '{}'", dir);
     let ctehexmlpath = ctehexml::find_ctehexml(&dir)?;
@@ -120,25 +172,48 @@ pub fn cli_main() -> Result<()> {
     };
 
     // Lee datos
-    let data = collect_hulc_data(ctehexmlpath, kygpath, tblpath)?;
+    let ctehexmlpath = ctehexmlpath.ok_or_else(|| {
+        format_err!("No se ha podido localizar el archivo .ctehexml del proyecto")
+    })?;
+    let ctehexmldata = ctehexml::parse_with_catalog(&ctehexmlpath)?;
+    let ids = if opts.legacy_ids {
+        IdScheme::Legacy
+    } else {
+        IdScheme::Named
+    };
+    let data = collect_hulc_data_from_ctehexml(
+        &ctehexmldata,
+        kygpath,
+        tblpath,
+        opts.window_u_method,
+        ids,
+    )?;
 
     // Información general
     let climatezone = data.meta.climate;
-    let totradjul =
-        hulc2envolventecte::cte::climatedata::total_radiation_in_july_by_orientation(&climatezone);
     eprintln!(
         "ZC: {}, A_ref={:.2} m², V/A={:.2} m³/m², K={:.2} W/m²a, q_sol;jul={:.2} kWh/m².mes, n50(he2019)={:.2} 1/h, C_o(he2019)={:.2} m³/h·m², n50={:.2} 1/h, C_o={:.2} m³/h·m²",
         climatezone,
         data.a_ref(),
         data.compacity(),
         data.K_he2019().K,
-        data.q_soljul(&totradjul),
+        data.q_soljul(),
         data.n50_he2019().n50,
         data.C_o_he2019(),
         data.n50(),
         data.C_o()
     );
 
+    // Análisis por lotes
+    if let Some(batchpath) = &opts.batch {
+        eprintln!("Análisis por lotes de los escenarios de '{}'", batchpath);
+        let definition = BatchDefinition::from_json(&read_file(batchpath)?)?;
+        let results = data.run_batch(&ctehexmldata.bdldata.db, &definition)?;
+        eprintln!("Salida de resultados en formato CSV");
+        println!("{}", batch_results_to_csv(&definition, &results));
+        return Ok(());
+    }
+
     // Convierte a JSON
     match data.as_json() {
         Ok(json) => {
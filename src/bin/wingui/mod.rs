@@ -36,7 +36,7 @@ use log4rs::encode::pattern::PatternEncoder;
 
 use hulc2envolventecte::{
     collect_hulc_data,
-    cte::climatedata::total_radiation_in_july_by_orientation,
+    cte::{IdScheme, WindowUMethod},
     get_copytxt,
     parsers::{ctehexml, kyg, tbl},
 };
@@ -461,6 +461,8 @@ fn do_convert() {
         ctehexmlpath.as_ref(),
         kygpath.as_ref(),
         tblpath.as_ref(),
+        WindowUMethod::default(),
+        IdScheme::default(),
     ) {
         Ok(data) => {
             append_to_edit("\nLeídos datos envolvente");
@@ -506,7 +508,6 @@ fn do_convert() {
     };
 
     let climatezone = envolvente_data.meta.climate;
-    let totradjul = total_radiation_in_july_by_orientation(&climatezone);
     append_to_edit(
         &format!(
             "\n\nDatos generales:\n\nZC: {}, A_ref={:.2} m², V/A={:.2} m³/m²\n- K={:.2} W/m²a\n- q_sol;jul={:.2} kWh/m².mes\n- n50(he2019)={:.2} 1/h, C_o(he2019)={:.2} m³/h·m², n50={:.2} 1/h, C_o={:.2} m³/h·m²",
@@ -514,7 +515,7 @@ fn do_convert() {
             envolvente_data.a_ref(),
             envolvente_data.compacity(),
             envolvente_data.K_he2019().K,
-            envolvente_data.q_soljul(&totradjul),
+            envolvente_data.q_soljul(),
             envolvente_data.n50_he2019().n50,
             envolvente_data.C_o_he2019(),
             envolvente_data.n50(),
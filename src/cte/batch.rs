@@ -0,0 +1,403 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Análisis por lotes de escenarios y de sensibilidad
+//!
+//! A partir de una definición en JSON con las variaciones de cada parámetro (sustitución de
+//! construcciones, espesores de aislamiento, factores solares, factores de obstáculos remotos y
+//! valores de ensayo de n50) se evalúan todas las combinaciones y se obtienen los indicadores
+//! (K, q_sol;jul, n50) y su cumplimiento de los valores límite del DB-HE.
+//!
+//! Ejemplo de definición:
+//! ```json
+//! {
+//!     "name": "Mejora de fachada",
+//!     "parameters": [
+//!         { "type": "LayerThickness", "wallcons": "SATE", "material": "MW Lana mineral [0.031 W/[mK]]", "values": [0.04, 0.08] },
+//!         { "type": "WindowG", "wincons": "PVC 2", "values": [0.3, 0.5] },
+//!         { "type": "N50Test", "values": [null, 3.0] }
+//!     ]
+//! }
+//! ```
+
+#![allow(non_snake_case)]
+
+use anyhow::{format_err, Error};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    from_ctehexml::{wallcons_from_bdl_cons, windowcons_from_bdl_cons},
+    BatchResult, ConsChange, Model,
+};
+use crate::bdl;
+
+/// Definición de un análisis por lotes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchDefinition {
+    /// Nombre del análisis
+    #[serde(default)]
+    pub name: String,
+    /// Parámetros que varían y sus valores
+    pub parameters: Vec<BatchParameter>,
+}
+
+/// Parámetro de un análisis por lotes y lista de valores
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BatchParameter {
+    /// Sustitución de una construcción de opaco por otras (del modelo o de la base de datos)
+    WallConsSwap {
+        wallcons: String,
+        options: Vec<String>,
+    },
+    /// Sustitución de una construcción de hueco por otras (del modelo o de la base de datos)
+    /// La U de las construcciones de la base de datos se calcula con el método de HULC (ver Model::apply_cons_changes)
+    WindowConsSwap {
+        wincons: String,
+        options: Vec<String>,
+    },
+    /// Espesor de las capas de un material de una composición de opaco (m)
+    LayerThickness {
+        wallcons: String,
+        material: String,
+        values: Vec<f32>,
+    },
+    /// Factor solar con la protección solar activada, g_gl;sh;wi, de una construcción de hueco
+    WindowG { wincons: String, values: Vec<f32> },
+    /// Factor de obstáculos remotos de los huecos indicados (de todos si no se indica ninguno)
+    Fshobst {
+        #[serde(default)]
+        windows: Vec<String>,
+        values: Vec<f32>,
+    },
+    /// Valor de n50 de ensayo (null para no considerar ensayo)
+    N50Test { values: Vec<Option<f32>> },
+}
+
+impl BatchParameter {
+    /// Número de valores del parámetro
+    pub fn len(&self) -> usize {
+        use BatchParameter::*;
+        match self {
+            WallConsSwap { options, .. } | WindowConsSwap { options, .. } => options.len(),
+            LayerThickness { values, .. } | WindowG { values, .. } | Fshobst { values, .. } => {
+                values.len()
+            }
+            N50Test { values } => values.len(),
+        }
+    }
+
+    /// ¿No tiene valores?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Etiqueta descriptiva del parámetro
+    pub fn label(&self) -> String {
+        use BatchParameter::*;
+        match self {
+            WallConsSwap { wallcons, .. } => format!("Construcción {}", wallcons),
+            WindowConsSwap { wincons, .. } => format!("Construcción {}", wincons),
+            LayerThickness {
+                wallcons, material, ..
+            } => format!("Espesor {} en {}", material, wallcons),
+            WindowG { wincons, .. } => format!("g_gl;sh;wi {}", wincons),
+            Fshobst { windows, .. } if windows.is_empty() => "F_sh;obst".to_string(),
+            Fshobst { windows, .. } => format!("F_sh;obst {}", windows.join(" ")),
+            N50Test { .. } => "n50 ensayo".to_string(),
+        }
+    }
+
+    /// Etiqueta del valor i-ésimo del parámetro
+    pub fn value_label(&self, i: usize) -> String {
+        use BatchParameter::*;
+        match self {
+            WallConsSwap { options, .. } | WindowConsSwap { options, .. } => options[i].clone(),
+            LayerThickness { values, .. } | WindowG { values, .. } | Fshobst { values, .. } => {
+                format!("{}", values[i])
+            }
+            N50Test { values } => values[i].map(|v| format!("{}", v)).unwrap_or_default(),
+        }
+    }
+}
+
+impl BatchDefinition {
+    /// Lee una definición de análisis por lotes desde JSON
+    pub fn from_json(data: &str) -> Result<Self, Error> {
+        let def: BatchDefinition = serde_json::from_str(data)?;
+        if let Some(p) = def.parameters.iter().find(|p| p.is_empty()) {
+            return Err(format_err!("Parámetro sin valores: {}", p.label()));
+        }
+        Ok(def)
+    }
+
+    /// Número de combinaciones de valores de los parámetros
+    pub fn num_combinations(&self) -> usize {
+        self.parameters.iter().map(|p| p.len()).product()
+    }
+
+    /// Índices de los valores de cada parámetro para la combinación n-ésima
+    /// El último parámetro es el que varía más rápido
+    fn combination(&self, mut n: usize) -> Vec<usize> {
+        let mut idx = vec![0; self.parameters.len()];
+        for (i, p) in self.parameters.iter().enumerate().rev() {
+            idx[i] = n % p.len();
+            n /= p.len();
+        }
+        idx
+    }
+}
+
+impl Model {
+    /// Evalúa todas las combinaciones de valores de un análisis por lotes
+    ///
+    /// db es la base de datos BDL con las composiciones, materiales, vidrios y marcos del proyecto
+    /// y del catálogo, que se usa para las sustituciones y modificaciones de construcciones.
+    pub fn run_batch(
+        &self,
+        db: &bdl::DB,
+        definition: &BatchDefinition,
+    ) -> Result<Vec<BatchResult>, Error> {
+        let num = definition.num_combinations();
+        info!(
+            "Análisis por lotes {}: {} parámetros, {} combinaciones",
+            definition.name,
+            definition.parameters.len(),
+            num
+        );
+        (0..num)
+            .map(|n| {
+                let idx = definition.combination(n);
+                let model = self.batch_variant(db, definition, &idx)?;
+                Ok(BatchResult::new(
+                    n,
+                    definition
+                        .parameters
+                        .iter()
+                        .zip(&idx)
+                        .map(|(p, &i)| p.value_label(i))
+                        .collect(),
+                    &model,
+                ))
+            })
+            .collect()
+    }
+
+    /// Modelo con los valores de los parámetros indicados
+    ///
+    /// Se aplican en primer lugar las sustituciones de construcciones y luego el resto de cambios
+    fn batch_variant(
+        &self,
+        db: &bdl::DB,
+        definition: &BatchDefinition,
+        idx: &[usize],
+    ) -> Result<Model, Error> {
+        use BatchParameter::*;
+        let mut model = self.clone();
+
+        for (param, &i) in definition.parameters.iter().zip(idx) {
+            match param {
+                WallConsSwap { wallcons, options } => {
+                    model.swap_wallcons(db, wallcons, &options[i])?
+                }
+                WindowConsSwap { wincons, options } => {
+                    model.swap_wincons(db, wincons, &options[i])?
+                }
+                _ => (),
+            }
+        }
+
+        let changes = definition
+            .parameters
+            .iter()
+            .zip(idx)
+            .filter_map(|(param, &i)| match param {
+                LayerThickness {
+                    wallcons,
+                    material,
+                    values,
+                } => Some(ConsChange::SetLayerThickness {
+                    wallcons: wallcons.clone(),
+                    material: material.clone(),
+                    thickness: values[i],
+                }),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !changes.is_empty() {
+            model = model.apply_cons_changes(db, &changes)?.0;
+        }
+
+        for (param, &i) in definition.parameters.iter().zip(idx) {
+            match param {
+                WindowG { wincons, values } => {
+                    let cons = model
+                        .wincons
+                        .iter_mut()
+                        .find(|c| &c.name == wincons)
+                        .ok_or_else(|| {
+                            format_err!("Construcción de hueco no encontrada: {}", wincons)
+                        })?;
+                    cons.gglshwi = values[i];
+                }
+                Fshobst { windows, values } => {
+                    for win in model
+                        .windows
+                        .iter_mut()
+                        .filter(|w| windows.is_empty() || windows.contains(&w.name))
+                    {
+                        win.fshobst = values[i];
+                    }
+                }
+                N50Test { values } => model.meta.n50_test_ach = values[i],
+                _ => (),
+            }
+        }
+        Ok(model)
+    }
+
+    /// Sustituye en los opacos una construcción por otra, del modelo o de la base de datos
+    fn swap_wallcons(&mut self, db: &bdl::DB, from: &str, to: &str) -> Result<(), Error> {
+        if from == to {
+            return Ok(());
+        }
+        let from_id = self
+            .wallcons
+            .iter()
+            .find(|c| c.name == from)
+            .map(|c| c.id.clone())
+            .ok_or_else(|| format_err!("Construcción de opaco no encontrada: {}", from))?;
+        let to_id = match self.wallcons.iter().find(|c| c.name == to) {
+            Some(cons) => cons.id.clone(),
+            None => {
+                let cons = db
+                    .wallcons
+                    .get(to)
+                    .ok_or_else(|| format_err!("Composición de opaco no encontrada: {}", to))?;
+                let mut cons = wallcons_from_bdl_cons(cons, &db.materials)?;
+                cons.id = self.named_id("WallCons", &cons.name);
+                let id = cons.id.clone();
+                self.wallcons.push(cons);
+                id
+            }
+        };
+        for wall in self.walls.iter_mut().filter(|w| w.cons == from_id) {
+            wall.cons = to_id.clone();
+        }
+        Ok(())
+    }
+
+    /// Sustituye en los huecos una construcción por otra, del modelo o de la base de datos
+    fn swap_wincons(&mut self, db: &bdl::DB, from: &str, to: &str) -> Result<(), Error> {
+        if from == to {
+            return Ok(());
+        }
+        let from_id = self
+            .wincons
+            .iter()
+            .find(|c| c.name == from)
+            .map(|c| c.id.clone())
+            .ok_or_else(|| format_err!("Construcción de hueco no encontrada: {}", from))?;
+        let to_id = match self.wincons.iter().find(|c| c.name == to) {
+            Some(cons) => cons.id.clone(),
+            None => {
+                let cons = db
+                    .windowcons
+                    .get(to)
+                    .ok_or_else(|| format_err!("Construcción de hueco no encontrada: {}", to))?;
+                let u = cons.u(&db.frames, &db.glasses)?;
+                let mut cons = windowcons_from_bdl_cons(cons, db, u)?;
+                cons.id = self.named_id("WindowCons", &cons.name);
+                let id = cons.id.clone();
+                self.wincons.push(cons);
+                id
+            }
+        };
+        for win in self.windows.iter_mut().filter(|w| w.cons == from_id) {
+            win.cons = to_id.clone();
+        }
+        Ok(())
+    }
+}
+
+impl BatchResult {
+    /// Resultado de una combinación con los indicadores del modelo y su comprobación
+    pub fn new(id: usize, values: Vec<String>, model: &Model) -> Self {
+        let u_values = model.u_values();
+        let (K, K_lim) = (model.K_he2019_with(&u_values).K, model.K_lim());
+        let (q_soljul, q_soljul_lim) = (model.q_soljul_with(&u_values), model.q_soljul_lim());
+        let (n50, n50_lim) = (model.n50(), model.n50_lim());
+        let K_ok = K <= K_lim;
+        let q_soljul_ok = q_soljul <= q_soljul_lim;
+        let n50_ok = n50_lim.map(|lim| n50 <= lim).unwrap_or(true);
+        BatchResult {
+            id,
+            values,
+            K,
+            K_lim,
+            q_soljul,
+            q_soljul_lim,
+            n50,
+            n50_lim,
+            compacity: model.compacity(),
+            K_ok,
+            q_soljul_ok,
+            n50_ok,
+            ok: K_ok && q_soljul_ok && n50_ok,
+        }
+    }
+}
+
+/// Tabla de resultados de un análisis por lotes en formato CSV
+pub fn batch_results_to_csv(definition: &BatchDefinition, results: &[BatchResult]) -> String {
+    let mut header = vec!["id".to_string()];
+    header.extend(definition.parameters.iter().map(|p| p.label()));
+    header.extend(
+        [
+            "K",
+            "K_lim",
+            "q_soljul",
+            "q_soljul_lim",
+            "n50",
+            "n50_lim",
+            "V/A",
+            "K_ok",
+            "q_soljul_ok",
+            "n50_ok",
+            "ok",
+        ]
+        .iter()
+        .map(|s| s.to_string()),
+    );
+    let mut lines = vec![header
+        .iter()
+        .map(|s| csv_str(s))
+        .collect::<Vec<_>>()
+        .join(",")];
+    for r in results {
+        let mut row = vec![r.id.to_string()];
+        row.extend(r.values.iter().map(|v| csv_str(v)));
+        row.extend(vec![
+            format!("{:.2}", r.K),
+            format!("{:.2}", r.K_lim),
+            format!("{:.2}", r.q_soljul),
+            format!("{:.2}", r.q_soljul_lim),
+            format!("{:.2}", r.n50),
+            r.n50_lim.map(|v| format!("{:.2}", v)).unwrap_or_default(),
+            format!("{:.2}", r.compacity),
+            r.K_ok.to_string(),
+            r.q_soljul_ok.to_string(),
+            r.n50_ok.to_string(),
+            r.ok.to_string(),
+        ]);
+        lines.push(row.join(","));
+    }
+    lines.join("\n")
+}
+
+/// Cadena entrecomillada para CSV
+fn csv_str(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
@@ -0,0 +1,169 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Factor de reducción de temperatura (b_tr) de los espacios no acondicionados o no habitables
+//! - UNE-EN ISO 13789:2017, apartado 6.4
+//!
+//! b_tr = H_ue / (H_iu + H_ue), donde H_iu es el coeficiente de transmisión de calor entre los
+//! espacios acondicionados y el espacio no acondicionado y H_ue el del espacio no acondicionado
+//! al exterior (transmisión y ventilación)
+
+#![allow(non_snake_case)]
+
+use std::collections::HashMap;
+
+use log::debug;
+
+use super::{
+    ground::THETA_I_DEFAULT, model_impl::r_f_interior, BoundaryType::*, BtrDetail, Model,
+    ModelIndex, MonthlyClimate, Space, SpaceType, Tilt, UValues, Wall,
+};
+
+impl BtrDetail {
+    /// Temperatura media mensual estimada del espacio (ºC)
+    ///
+    /// θ_u = θ_i - b_tr · (θ_i - θ_e), con las temperaturas interiores por defecto de los espacios
+    /// acondicionados (ver THETA_I_DEFAULT) y las exteriores medias mensuales indicadas
+    pub fn temp(&self, climate: &MonthlyClimate) -> [f32; 12] {
+        let mut temp = [0.0; 12];
+        for m in 0..12 {
+            temp[m] = THETA_I_DEFAULT[m] - self.btr * (THETA_I_DEFAULT[m] - climate.temp[m]);
+        }
+        temp
+    }
+}
+
+impl Model {
+    /// Factores b_tr de los espacios no acondicionados y no habitables del modelo
+    ///
+    /// La temperatura mensual estimada de cada espacio se obtiene con BtrDetail::temp
+    pub fn btr_report(&self) -> Vec<BtrDetail> {
+        self.btr_report_with(&self.u_values())
+    }
+
+    /// btr_report con las transmitancias térmicas ya calculadas (ver Model::u_values)
+    pub fn btr_report_with(&self, u_values: &UValues) -> Vec<BtrDetail> {
+        self.spaces
+            .iter()
+            .filter(|s| s.space_type != SpaceType::CONDITIONED)
+            .map(|s| self.btr_for_space_with(s, u_values))
+            .collect()
+    }
+
+    /// Factor b_tr de un espacio no acondicionado o no habitable
+    pub fn btr_for_space(&self, space: &Space) -> BtrDetail {
+        self.btr_for_space_with(space, &self.u_values())
+    }
+
+    /// btr_for_space con las transmitancias térmicas ya calculadas (ver Model::u_values)
+    pub fn btr_for_space_with(&self, space: &Space, u_values: &UValues) -> BtrDetail {
+        let index = &u_values.index;
+        let H_iu = index
+            .walls_of_space(&space.id)
+            .filter(|w| w.bounds == INTERIOR)
+            .filter_map(|w| h_iu_for_wall(index, w, space))
+            .sum::<f32>();
+        let (UA_e_k, n_v, volume) = self.h_ue_of_space(space, &u_values.walls, &u_values.index);
+        let H_ve = 0.33 * n_v * volume;
+        let H_ue = UA_e_k + H_ve;
+        let btr = if H_iu + H_ue > 0.0 {
+            H_ue / (H_iu + H_ue)
+        } else {
+            1.0
+        };
+        debug!(
+            "{} (b_tr={:.2}) H_iu={:.2}, H_ue={:.2} (A·U={:.2}, H_ve={:.2}, n={:.2}, V={:.2})",
+            space.name, btr, H_iu, H_ue, UA_e_k, H_ve, n_v, volume
+        );
+
+        BtrDetail {
+            space: space.id.clone(),
+            name: space.name.clone(),
+            h_iu: H_iu,
+            ua_ue: UA_e_k,
+            h_ve: H_ve,
+            h_ue: H_ue,
+            n_v,
+            volume,
+            btr,
+        }
+    }
+
+    /// Factores b_tr de los espacios no acondicionados y no habitables, por id de espacio
+    pub fn btr_of_spaces(&self, u_values: &UValues) -> HashMap<String, f32> {
+        self.spaces
+            .iter()
+            .filter(|s| s.space_type != SpaceType::CONDITIONED)
+            .map(|s| (s.id.clone(), self.btr_for_space_with(s, u_values).btr))
+            .collect()
+    }
+
+    /// Factor b_tr de un elemento opaco
+    ///
+    /// - 1.0 para elementos en contacto con el exterior o el terreno
+    /// - 0.0 para elementos adiabáticos y particiones entre espacios acondicionados
+    /// - el b_tr del espacio no acondicionado para particiones con estos espacios
+    ///
+    /// Para obtener el b_tr de muchos elementos es preferible usar btr_for_wall_from
+    pub fn btr_for_wall(&self, wall: &Wall) -> Option<f32> {
+        self.btr_for_wall_with(wall, &self.u_values())
+    }
+
+    /// btr_for_wall con las transmitancias térmicas ya calculadas (ver Model::u_values)
+    pub fn btr_for_wall_with(&self, wall: &Wall, u_values: &UValues) -> Option<f32> {
+        btr_for_wall_by(wall, u_values, |space| {
+            Some(self.btr_for_space_with(space, u_values).btr)
+        })
+    }
+
+    /// Factor b_tr de un elemento opaco con los b_tr de los espacios ya calculados (ver btr_of_spaces)
+    pub fn btr_for_wall_from(
+        &self,
+        wall: &Wall,
+        u_values: &UValues,
+        btrs: &HashMap<String, f32>,
+    ) -> Option<f32> {
+        btr_for_wall_by(wall, u_values, |space| btrs.get(&space.id).copied())
+    }
+}
+
+/// Factor b_tr de un elemento opaco, con el b_tr de los espacios no acondicionados dado por space_btr
+fn btr_for_wall_by<F>(wall: &Wall, u_values: &UValues, space_btr: F) -> Option<f32>
+where
+    F: Fn(&Space) -> Option<f32>,
+{
+    match wall.bounds {
+        EXTERIOR | GROUND => Some(1.0),
+        ADIABATIC => Some(0.0),
+        INTERIOR => {
+            let space = u_values.index.get_wallspace(wall)?;
+            let nextspace = u_values.index.get_space(wall.nextto.as_ref()?)?;
+            match (space.space_type, nextspace.space_type) {
+                (SpaceType::CONDITIONED, SpaceType::CONDITIONED) => Some(0.0),
+                (SpaceType::CONDITIONED, _) => space_btr(nextspace),
+                (_, SpaceType::CONDITIONED) => space_btr(space),
+                // Particiones entre espacios no acondicionados
+                _ => Some(0.0),
+            }
+        }
+    }
+}
+
+/// Coeficiente H_iu = A / R_f de una partición entre un espacio acondicionado y el espacio
+/// no acondicionado dado
+fn h_iu_for_wall(index: &ModelIndex, wall: &Wall, uncondspace: &Space) -> Option<f32> {
+    let (condspace, thiscondspace) = if wall.space == uncondspace.id {
+        (index.get_space(wall.nextto.as_ref()?)?, false)
+    } else if wall.nextto.as_deref() == Some(uncondspace.id.as_str()) {
+        (index.get_wallspace(wall)?, true)
+    } else {
+        return None;
+    };
+    if condspace.space_type != SpaceType::CONDITIONED {
+        return None;
+    }
+    let cons = index.get_wallcons(wall)?;
+    let R_f = r_f_interior(Tilt::from(wall), thiscondspace, cons.r_intrinsic);
+    Some(wall.area / R_f)
+}
@@ -0,0 +1,407 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Generación de modelos de edificios sintéticos a partir de una definición simplificada
+//!
+//! El edificio se define por su planta (polígono), el número y la altura de las plantas, la
+//! proporción de huecos en cada orientación y las construcciones, que se toman de una base de
+//! datos BDL (p.e. el catálogo de HULC, ver ctehexml::catalog).
+//!
+//! Cada planta es un espacio acondicionado con un opaco de fachada por cada lado del polígono (con
+//! un hueco si hay huecos en esa orientación), un suelo (en contacto con el terreno en la planta
+//! baja o forjado interior sobre la planta inferior) y, en la última planta, la cubierta. Se
+//! incluyen los puentes térmicos habituales, con las longitudes que resultan de la geometría y las
+//! transmitancias térmicas lineales indicadas en la definición.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, format_err, Error};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    from_ctehexml::{wallcons_from_bdl_cons, windowcons_from_bdl_cons},
+    reference::psi_for_name,
+    BoundaryType, ClimateZone, Meta, Model, Orientation, Space, SpaceType, ThermalBridge, Wall,
+    Window,
+};
+use crate::{
+    bdl,
+    utils::{fround2, orientation_bdl_to_52016},
+};
+
+/// Definición de un edificio sintético
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuildingDefinition {
+    /// Nombre del proyecto
+    pub name: String,
+    /// Zona climática
+    pub climate: ClimateZone,
+    /// ¿Es uso residencial?
+    pub is_dwelling: bool,
+    /// Vértices (x, y) de la planta del edificio (m), con el eje Y hacia el Norte
+    pub footprint: Vec<(f32, f32)>,
+    /// Número de plantas
+    pub floors: u32,
+    /// Altura bruta (suelo a suelo) de las plantas (m)
+    pub floor_height: f32,
+    /// Proporción de huecos respecto a la superficie bruta de fachada en cada orientación [0-1]
+    pub wwr: HashMap<Orientation, f32>,
+    /// Altura de los huecos (m), para obtener su perímetro
+    /// Si la anchura resultante supera la longitud de la fachada el hueco ocupa toda su longitud
+    /// y se aumenta su altura
+    pub window_height: f32,
+    /// Composición de las fachadas
+    pub wallcons: String,
+    /// Composición de la cubierta
+    pub roofcons: String,
+    /// Composición del suelo en contacto con el terreno
+    pub groundcons: String,
+    /// Composición de los forjados entre plantas
+    pub floorcons: String,
+    /// Construcción de los huecos
+    pub wincons: String,
+    /// Transmitancias térmicas lineales por tipo de puente térmico (W/mK), p.e. las del DA DB-HE/3
+    /// (ver reference::psi_for_name)
+    pub psi: BTreeMap<String, f32>,
+    /// Ventilación de los espacios, en ren/h
+    pub n_v: Option<f32>,
+    /// Ventilación global del edificio, para uso residencial, en l/s
+    pub global_ventilation_l_s: Option<f32>,
+}
+
+impl Default for BuildingDefinition {
+    fn default() -> Self {
+        BuildingDefinition {
+            name: "Edificio".to_string(),
+            climate: ClimateZone::D3,
+            is_dwelling: true,
+            footprint: vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)],
+            floors: 1,
+            floor_height: 3.0,
+            wwr: HashMap::new(),
+            window_height: 1.5,
+            wallcons: "Fachada por defecto D".to_string(),
+            roofcons: "Cubierta por defecto C, D".to_string(),
+            groundcons: "Suelo por defecto A, B, C, D, E".to_string(),
+            floorcons: "PIH por defecto".to_string(),
+            wincons: "Doble bajo emisivo -- Mpvc o mad - Gris claro".to_string(),
+            psi: BTreeMap::new(),
+            n_v: None,
+            global_ventilation_l_s: None,
+        }
+    }
+}
+
+impl BuildingDefinition {
+    /// Edificio de planta rectangular con el ancho (dirección Este-Oeste) y fondo (dirección
+    /// Norte-Sur) dados (m) y el número de plantas indicado
+    pub fn box_building(width: f32, depth: f32, floors: u32) -> Self {
+        BuildingDefinition {
+            footprint: vec![(0.0, 0.0), (width, 0.0), (width, depth), (0.0, depth)],
+            floors,
+            ..Default::default()
+        }
+    }
+}
+
+impl Model {
+    /// Genera el modelo de un edificio sintético con las construcciones de la base de datos
+    pub fn from_definition(def: &BuildingDefinition, db: &bdl::DB) -> Result<Model, Error> {
+        if def.footprint.len() < 3 {
+            bail!("La planta del edificio debe tener al menos 3 vértices");
+        }
+        if def.floors == 0 || def.floor_height <= 0.0 || def.window_height <= 0.0 {
+            bail!("El número de plantas y las alturas de plantas y huecos deben ser positivos");
+        }
+        if def.window_height > def.floor_height {
+            bail!("La altura de los huecos no puede superar la altura de las plantas");
+        }
+        if !is_simple_polygon(&def.footprint) {
+            bail!("La planta del edificio tiene lados de longitud nula o que se cortan");
+        }
+        if let Some((orientation, wwr)) = def.wwr.iter().find(|(_, v)| !(0.0..1.0).contains(*v)) {
+            bail!(
+                "Proporción de huecos fuera de rango en la orientación {}: {}",
+                orientation,
+                wwr
+            );
+        }
+
+        // Vértices en sentido antihorario, de modo que la normal exterior de cada lado es (dy, -dx)
+        let mut points = def.footprint.clone();
+        let signed_area = 0.5
+            * points
+                .iter()
+                .zip(points.iter().cycle().skip(1))
+                .map(|((x0, y0), (x1, y1))| x0 * y1 - x1 * y0)
+                .sum::<f32>();
+        if signed_area.abs() < 0.01 {
+            bail!("La planta del edificio no tiene superficie");
+        }
+        if signed_area < 0.0 {
+            points.reverse();
+        }
+        let area = signed_area.abs();
+        let sides = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(&(x0, y0), &(x1, y1))| (x1 - x0, y1 - y0))
+            .collect::<Vec<_>>();
+        let perimeter = sides.iter().map(|(dx, dy)| dx.hypot(*dy)).sum::<f32>();
+
+        let mut model = Model {
+            meta: Meta {
+                name: def.name.clone(),
+                is_dwelling: def.is_dwelling,
+                climate: def.climate,
+                global_ventilation_l_s: def.global_ventilation_l_s,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        model.meta.id_namespace = Some(model.id_namespace());
+
+        let wallcons = add_db_wallcons(&mut model, db, &def.wallcons)?;
+        let roofcons = add_db_wallcons(&mut model, db, &def.roofcons)?;
+        let groundcons = add_db_wallcons(&mut model, db, &def.groundcons)?;
+        let floorcons = if def.floors > 1 {
+            Some(add_db_wallcons(&mut model, db, &def.floorcons)?)
+        } else {
+            None
+        };
+        let wincons = if def.wwr.values().any(|&v| v > 0.0) {
+            Some(add_db_wincons(&mut model, db, &def.wincons)?)
+        } else {
+            None
+        };
+
+        let h = def.floor_height;
+        let mut windows_perimeter = 0.0;
+        let mut lower_space: Option<String> = None;
+        for floor in 0..def.floors {
+            let spacename = format!("P{:02}_E01", floor + 1);
+            let space = model.add_space(Space {
+                id: String::new(),
+                name: spacename.clone(),
+                area: fround2(area),
+                multiplier: 1.0,
+                space_type: SpaceType::CONDITIONED,
+                inside_tenv: true,
+                height: h,
+                n_v: def.n_v,
+                z: fround2(floor as f32 * h),
+                exposed_perimeter: Some(fround2(perimeter)),
+                buried_depth: None,
+                crawl_space: None,
+                conditions: None,
+            })?;
+
+            // Suelo en contacto con el terreno o forjado sobre la planta inferior
+            let (name, bounds, cons) = match (&lower_space, &floorcons) {
+                (Some(_), Some(floorcons)) => (
+                    format!("{}_FI001", spacename),
+                    BoundaryType::INTERIOR,
+                    floorcons,
+                ),
+                _ => (
+                    format!("{}_FTER001", spacename),
+                    BoundaryType::GROUND,
+                    &groundcons,
+                ),
+            };
+            model.add_wall(Wall {
+                id: String::new(),
+                name,
+                area: fround2(area),
+                bounds,
+                cons: cons.clone(),
+                space: space.clone(),
+                nextto: lower_space.clone(),
+                azimuth: 0.0,
+                tilt: 180.0,
+            })?;
+
+            // Fachadas y huecos
+            for (i, (dx, dy)) in sides.iter().enumerate() {
+                let bdl_azimuth = dy.atan2(-dx).to_degrees();
+                let azimuth = fround2(orientation_bdl_to_52016(bdl_azimuth));
+                let length = dx.hypot(*dy);
+                let gross_area = length * h;
+                let wwr = def
+                    .wwr
+                    .get(&Orientation::from(azimuth))
+                    .copied()
+                    .unwrap_or(0.0);
+                let win_area = fround2(gross_area * wwr);
+                let wallname = format!("{}_PE{:03}", spacename, i + 1);
+                let wall = model.add_wall(Wall {
+                    id: String::new(),
+                    name: wallname.clone(),
+                    area: fround2(gross_area - win_area),
+                    bounds: BoundaryType::EXTERIOR,
+                    cons: wallcons.clone(),
+                    space: space.clone(),
+                    nextto: None,
+                    azimuth,
+                    tilt: 90.0,
+                })?;
+                if let (true, Some(wincons)) = (win_area > 0.0, &wincons) {
+                    model.add_window(Window {
+                        id: String::new(),
+                        name: format!("{}_V", wallname),
+                        area: win_area,
+                        cons: wincons.clone(),
+                        wall,
+                        fshobst: 1.0,
+                    })?;
+                    // Hueco de la altura indicada o, si no cabe, de la longitud de la fachada
+                    let (win_width, win_height) = if win_area / def.window_height > length {
+                        (length, win_area / length)
+                    } else {
+                        (win_area / def.window_height, def.window_height)
+                    };
+                    windows_perimeter += 2.0 * (win_width + win_height);
+                }
+            }
+
+            // Cubierta
+            if floor + 1 == def.floors {
+                model.add_wall(Wall {
+                    id: String::new(),
+                    name: format!("{}_CUB001", spacename),
+                    area: fround2(area),
+                    bounds: BoundaryType::EXTERIOR,
+                    cons: roofcons.clone(),
+                    space: space.clone(),
+                    nextto: None,
+                    azimuth: 0.0,
+                    tilt: 0.0,
+                })?;
+            }
+            lower_space = Some(space);
+        }
+
+        // Puentes térmicos
+        let (mut convex, mut concave) = (0, 0);
+        for (i, (dx0, dy0)) in sides.iter().enumerate() {
+            let (dx1, dy1) = sides[(i + 1) % sides.len()];
+            let cross = dx0 * dy1 - dy0 * dx1;
+            if cross > 0.0 {
+                convex += 1;
+            } else if cross < 0.0 {
+                concave += 1;
+            }
+        }
+        let height = def.floors as f32 * h;
+        let tbs = [
+            ("FRENTE_FORJADO", perimeter * (def.floors - 1) as f32),
+            ("UNION_CUBIERTA", perimeter),
+            ("UNION_SOLERA_PAREDEXT", perimeter),
+            ("ESQUINA_CONVEXA", convex as f32 * height),
+            ("ESQUINA_CONCAVA", concave as f32 * height),
+            ("HUECO_VENTANA", windows_perimeter),
+        ];
+        for (name, l) in tbs.iter().filter(|(_, l)| *l > 0.0) {
+            let psi = psi_for_name(&def.psi, name).unwrap_or_else(|| {
+                warn!(
+                    "Puente térmico {} sin transmitancia definida, psi=0.00",
+                    name
+                );
+                0.0
+            });
+            model.add_thermal_bridge(ThermalBridge {
+                id: String::new(),
+                name: name.to_string(),
+                l: fround2(*l),
+                psi,
+                frsi: None,
+            })?;
+        }
+
+        info!(
+            "Generado el modelo {} con {} plantas, {} opacos y {} huecos",
+            def.name,
+            def.floors,
+            model.walls.len(),
+            model.windows.len()
+        );
+        Ok(model)
+    }
+}
+
+/// ¿Es el polígono simple? (sin lados de longitud nula ni lados que se cortan)
+fn is_simple_polygon(points: &[(f32, f32)]) -> bool {
+    let n = points.len();
+    let side = |i: usize| (points[i], points[(i + 1) % n]);
+    if (0..n).any(|i| {
+        let ((x0, y0), (x1, y1)) = side(i);
+        (x1 - x0).hypot(y1 - y0) < 0.001
+    }) {
+        return false;
+    }
+    for i in 0..n {
+        // Solo se comparan lados no consecutivos
+        for j in (i + 2)..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            let (a, b) = side(i);
+            let (c, d) = side(j);
+            if segments_intersect(a, b, c, d) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// ¿Se cortan o se tocan los segmentos ab y cd?
+fn segments_intersect(a: (f32, f32), b: (f32, f32), c: (f32, f32), d: (f32, f32)) -> bool {
+    let cross = |o: (f32, f32), p: (f32, f32), q: (f32, f32)| {
+        (p.0 - o.0) * (q.1 - o.1) - (p.1 - o.1) * (q.0 - o.0)
+    };
+    // Punto q sobre el segmento op, sabiendo que están alineados
+    let on_segment = |o: (f32, f32), p: (f32, f32), q: (f32, f32)| {
+        q.0 >= o.0.min(p.0) && q.0 <= o.0.max(p.0) && q.1 >= o.1.min(p.1) && q.1 <= o.1.max(p.1)
+    };
+    let (d1, d2) = (cross(c, d, a), cross(c, d, b));
+    let (d3, d4) = (cross(a, b, c), cross(a, b, d));
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+    {
+        return true;
+    }
+    (d1 == 0.0 && on_segment(c, d, a))
+        || (d2 == 0.0 && on_segment(c, d, b))
+        || (d3 == 0.0 && on_segment(a, b, c))
+        || (d4 == 0.0 && on_segment(a, b, d))
+}
+
+/// Añade al modelo una composición de opaco de la base de datos, si no existe ya, y devuelve su id
+fn add_db_wallcons(model: &mut Model, db: &bdl::DB, name: &str) -> Result<String, Error> {
+    if let Some(cons) = model.wallcons.iter().find(|c| c.name == name) {
+        return Ok(cons.id.clone());
+    }
+    let cons = db
+        .wallcons
+        .get(name)
+        .ok_or_else(|| format_err!("Composición de opaco no encontrada: {}", name))?;
+    let mut cons = wallcons_from_bdl_cons(cons, &db.materials)?;
+    cons.id = String::new();
+    model.add_wallcons(cons)
+}
+
+/// Añade al modelo una construcción de hueco de la base de datos y devuelve su id
+fn add_db_wincons(model: &mut Model, db: &bdl::DB, name: &str) -> Result<String, Error> {
+    let cons = db
+        .windowcons
+        .get(name)
+        .ok_or_else(|| format_err!("Construcción de hueco no encontrada: {}", name))?;
+    let u = cons.u(&db.frames, &db.glasses)?;
+    let mut cons = windowcons_from_bdl_cons(cons, db, u)?;
+    cons.id = String::new();
+    model.add_wincons(cons)
+}
@@ -5,9 +5,12 @@
 //! Datos de radiación mensuales para superficies
 //! TODO: Convertir a orientación UNE-EN ISO 52016-1, medido desde el sur, positivo al este, negativo al oeste (S=0, E=+90, W=-90)
 
-use std::collections::HashMap;
+use std::{collections::HashMap, convert::TryFrom};
 
-use super::common::{ClimateZone, Orientation, SurfaceMonthlyRadiation};
+use super::common::{
+    ClimateZone, MonthlyClimate, Orientation, ProvinceClimateZones, SurfaceMonthlyRadiation,
+};
+use crate::parsers::met::MetData;
 use ClimateZone::*;
 use Orientation::*;
 
@@ -20,6 +23,552 @@ pub fn total_radiation_in_july_by_orientation(climate: &ClimateZone) -> HashMap<
         .collect()
 }
 
+/// Reflectancia solar del terreno para el cálculo de la radiación reflejada
+const ALBEDO: f32 = 0.2;
+
+/// Orientaciones e inclinaciones de la tabla de radiación por superficie (beta, gamma)
+const RADDATA_SURFACES: [(Orientation, f32, f32); 9] = [
+    (HZ, 0.0, 0.0),
+    (NE, 90.0, -135.0),
+    (E, 90.0, -90.0),
+    (SE, 90.0, -45.0),
+    (S, 90.0, 0.0),
+    (SW, 90.0, 45.0),
+    (W, 90.0, 90.0),
+    (NW, 90.0, 135.0),
+    (N, 90.0, 180.0),
+];
+
+/// Datos mensuales de radiación sobre una superficie a partir de los datos horarios de un archivo .met
+///
+/// - beta: inclinación de la superficie (Horiz=0, vertical=90)
+/// - gamma: orientación de la superficie (S=0, E=-90, W=+90, N=180)
+///
+/// La radiación difusa se calcula con un modelo de cielo isótropo y se añade la reflejada por el terreno.
+/// Los factores de uso de las protecciones solares móviles son la fracción de la radiación mensual
+/// recibida en las horas en las que la irradiancia sobre la superficie supera el nivel de activación
+pub fn surface_monthly_radiation_from_met(
+    met: &MetData,
+    zone: ClimateZone,
+    orientation: Orientation,
+    beta: f32,
+    gamma: f32,
+) -> SurfaceMonthlyRadiation {
+    let (cosb, sinb) = (beta.to_radians().cos(), beta.to_radians().sin());
+    let mut rad = SurfaceMonthlyRadiation {
+        zone,
+        orientation,
+        beta,
+        gamma,
+        dir: [0.0; 12],
+        dif: [0.0; 12],
+        tot: [0.0; 12],
+        f_shwith200: [0.0; 12],
+        f_shwith300: [0.0; 12],
+        f_shwith500: [0.0; 12],
+    };
+    for m in 0..12 {
+        let (mut dir, mut dif, mut over200, mut over300, mut over500) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for h in met.hours_of_month(m as u32 + 1) {
+            let (cosz, sinz) = (
+                h.sun_zenith.to_radians().cos(),
+                h.sun_zenith.to_radians().sin(),
+            );
+            // Irradiancia directa normal, descartando el sol muy próximo al horizonte
+            let beam = if cosz > 0.017 {
+                let cos_inc =
+                    cosb * cosz + sinb * sinz * (h.sun_azimuth - gamma).to_radians().cos();
+                h.rad_dir_h / cosz * cos_inc.max(0.0)
+            } else {
+                0.0
+            };
+            let diffuse =
+                h.rad_dif_h * (1.0 + cosb) / 2.0 + ALBEDO * h.rad_glob_h() * (1.0 - cosb) / 2.0;
+            let total = beam + diffuse;
+            dir += beam;
+            dif += diffuse;
+            if total > 200.0 {
+                over200 += total;
+            }
+            if total > 300.0 {
+                over300 += total;
+            }
+            if total > 500.0 {
+                over500 += total;
+            }
+        }
+        let tot = dir + dif;
+        // Wh/m2 -> kWh/m2
+        rad.dir[m] = dir / 1000.0;
+        rad.dif[m] = dif / 1000.0;
+        rad.tot[m] = tot / 1000.0;
+        if tot > 0.0 {
+            rad.f_shwith200[m] = over200 / tot;
+            rad.f_shwith300[m] = over300 / tot;
+            rad.f_shwith500[m] = over500 / tot;
+        }
+    }
+    rad
+}
+
+/// Regenera los datos mensuales de radiación de una zona climática (superficie horizontal y 8 orientaciones verticales)
+/// a partir de los datos horarios de un archivo .met
+pub fn raddata_from_met(met: &MetData, zone: ClimateZone) -> Vec<SurfaceMonthlyRadiation> {
+    RADDATA_SURFACES
+        .iter()
+        .map(|(orientation, beta, gamma)| {
+            surface_monthly_radiation_from_met(met, zone, *orientation, *beta, *gamma)
+        })
+        .collect()
+}
+
+/// Temperaturas y humedades relativas exteriores medias mensuales a partir de los datos horarios
+/// de un archivo .met (p.e. los archivos climáticos de referencia de cada zona del DB-HE)
+pub fn monthly_climate_from_met(met: &MetData, zone: ClimateZone) -> MonthlyClimate {
+    let mut climate = MonthlyClimate {
+        zone,
+        temp: [0.0; 12],
+        rh: [0.0; 12],
+    };
+    for m in 0..12 {
+        let (mut temp, mut rh, mut n) = (0.0, 0.0, 0);
+        for hour in met.hours_of_month(m as u32 + 1) {
+            temp += hour.temp;
+            rh += hour.rel_humidity;
+            n += 1;
+        }
+        if n > 0 {
+            climate.temp[m] = temp / n as f32;
+            climate.rh[m] = rh / n as f32;
+        }
+    }
+    climate
+}
+
+/// Zona climática del proyecto a partir de la zona y la comunidad autónoma indicadas en HULC
+///
+/// HULC guarda la zona sin distinguir los climas canarios (alfa1, A1, ...), por lo que se añade el
+/// sufijo de los climas canarios cuando la comunidad autónoma es Canarias.
+/// Devuelve None si no se reconoce la zona.
+pub fn climatezone_from_hulc(zona_climatica: &str, comunidad: &str) -> Option<ClimateZone> {
+    let zone = zona_climatica.trim();
+    if zone.is_empty() {
+        return None;
+    }
+    let zone = if normalize_name(comunidad) == "canarias" {
+        format!("{}c", zone)
+    } else {
+        zone.to_string()
+    };
+    ClimateZone::try_from(zone.as_str()).ok()
+}
+
+/// Zona climática de una localidad a partir de su provincia y altitud (m)
+/// DB-HE Anejo B. Zonas climáticas, tabla B.1
+///
+/// Se obtiene con el desnivel entre la localidad y la capital de su provincia. Cada tramo de desnivel
+/// incluye su valor mínimo y las localidades a menor altitud que la capital tienen la zona de esta.
+/// Devuelve None si no se encuentra la provincia.
+pub fn climatezone_from_location(province: &str, altitude: f32) -> Option<ClimateZone> {
+    let name = normalize_name(province);
+    let row = PROVINCE_CLIMATE_ZONES
+        .iter()
+        .find(|row| row.names.iter().any(|n| normalize_name(n) == name))?;
+    let step = ((altitude - row.altitude) / 200.0).floor();
+    if step < 1.0 {
+        Some(row.zone)
+    } else {
+        Some(row.steps[(step as usize - 1).min(4)])
+    }
+}
+
+/// Zonas climáticas de las provincias (DB-HE Anejo B, tabla B.1)
+///
+/// Capital de provincia, altitud de referencia y zonas climáticas según el desnivel de la localidad
+/// con la capital, incluidas las provincias canarias, con sus zonas propias (alfa3, A2, ...)
+pub static PROVINCE_CLIMATE_ZONES: [ProvinceClimateZones; 52] = [
+    ProvinceClimateZones {
+        names: &["Albacete"],
+        capital: "Albacete",
+        altitude: 677.0,
+        zone: D3,
+        steps: [D2, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Alicante", "Alacant"],
+        capital: "Alicante",
+        altitude: 7.0,
+        zone: B4,
+        steps: [C3, C1, D1, D1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Almería"],
+        capital: "Almería",
+        altitude: 0.0,
+        zone: A4,
+        steps: [B3, B3, C1, C1, D1],
+    },
+    ProvinceClimateZones {
+        names: &["Ávila"],
+        capital: "Ávila",
+        altitude: 1054.0,
+        zone: E1,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Badajoz"],
+        capital: "Badajoz",
+        altitude: 168.0,
+        zone: C4,
+        steps: [C3, D1, D1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Barcelona"],
+        capital: "Barcelona",
+        altitude: 1.0,
+        zone: C2,
+        steps: [C1, D1, D1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Vizcaya", "Bizkaia"],
+        capital: "Bilbao",
+        altitude: 214.0,
+        zone: C1,
+        steps: [D1, D1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Burgos"],
+        capital: "Burgos",
+        altitude: 861.0,
+        zone: E1,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Cáceres"],
+        capital: "Cáceres",
+        altitude: 385.0,
+        zone: C4,
+        steps: [D3, D1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Cádiz"],
+        capital: "Cádiz",
+        altitude: 0.0,
+        zone: A3,
+        steps: [B3, B3, C1, C1, D1],
+    },
+    ProvinceClimateZones {
+        names: &["Castellón", "Castelló"],
+        capital: "Castellón de la Plana",
+        altitude: 18.0,
+        zone: B3,
+        steps: [C2, C1, D1, D1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Ceuta"],
+        capital: "Ceuta",
+        altitude: 0.0,
+        zone: B3,
+        steps: [C1, C1, D1, D1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Ciudad Real"],
+        capital: "Ciudad Real",
+        altitude: 630.0,
+        zone: D3,
+        steps: [D2, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Córdoba"],
+        capital: "Córdoba",
+        altitude: 113.0,
+        zone: B4,
+        steps: [C3, C2, D1, D1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["A Coruña", "La Coruña", "Coruña"],
+        capital: "A Coruña",
+        altitude: 0.0,
+        zone: C1,
+        steps: [C1, D1, D1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Cuenca"],
+        capital: "Cuenca",
+        altitude: 975.0,
+        zone: D2,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Girona", "Gerona"],
+        capital: "Girona",
+        altitude: 143.0,
+        zone: C2,
+        steps: [D1, D1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Granada"],
+        capital: "Granada",
+        altitude: 754.0,
+        zone: C3,
+        steps: [D2, D1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Guadalajara"],
+        capital: "Guadalajara",
+        altitude: 708.0,
+        zone: D3,
+        steps: [D1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Guipúzcoa", "Gipuzkoa"],
+        capital: "San Sebastián",
+        altitude: 5.0,
+        zone: C1,
+        steps: [D1, D1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Huelva"],
+        capital: "Huelva",
+        altitude: 4.0,
+        zone: B4,
+        steps: [B3, C1, C1, D1, D1],
+    },
+    ProvinceClimateZones {
+        names: &["Huesca"],
+        capital: "Huesca",
+        altitude: 432.0,
+        zone: D2,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Illes Balears", "Islas Baleares", "Baleares", "Balears"],
+        capital: "Palma de Mallorca",
+        altitude: 1.0,
+        zone: B3,
+        steps: [B3, C1, C1, D1, D1],
+    },
+    ProvinceClimateZones {
+        names: &["Jaén"],
+        capital: "Jaén",
+        altitude: 436.0,
+        zone: C4,
+        steps: [C3, D2, D1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["La Rioja", "Rioja"],
+        capital: "Logroño",
+        altitude: 379.0,
+        zone: D2,
+        steps: [D1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Las Palmas"],
+        capital: "Las Palmas de Gran Canaria",
+        altitude: 114.0,
+        zone: Alfa3c,
+        steps: [A2c, A2c, A1c, A1c, B1c],
+    },
+    ProvinceClimateZones {
+        names: &["León"],
+        capital: "León",
+        altitude: 838.0,
+        zone: E1,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Lleida", "Lérida"],
+        capital: "Lleida",
+        altitude: 131.0,
+        zone: D3,
+        steps: [D2, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Lugo"],
+        capital: "Lugo",
+        altitude: 412.0,
+        zone: D1,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Madrid"],
+        capital: "Madrid",
+        altitude: 589.0,
+        zone: D3,
+        steps: [D1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Málaga"],
+        capital: "Málaga",
+        altitude: 0.0,
+        zone: A3,
+        steps: [B3, C1, C1, D1, D1],
+    },
+    ProvinceClimateZones {
+        names: &["Melilla"],
+        capital: "Melilla",
+        altitude: 130.0,
+        zone: A3,
+        steps: [B3, B3, C1, C1, D1],
+    },
+    ProvinceClimateZones {
+        names: &["Murcia"],
+        capital: "Murcia",
+        altitude: 25.0,
+        zone: B3,
+        steps: [C2, C1, D1, D1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Navarra", "Nafarroa"],
+        capital: "Pamplona",
+        altitude: 456.0,
+        zone: D1,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Ourense", "Orense"],
+        capital: "Ourense",
+        altitude: 327.0,
+        zone: C2,
+        steps: [D1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Asturias"],
+        capital: "Oviedo",
+        altitude: 214.0,
+        zone: C1,
+        steps: [D1, D1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Palencia"],
+        capital: "Palencia",
+        altitude: 722.0,
+        zone: D1,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Pontevedra"],
+        capital: "Pontevedra",
+        altitude: 77.0,
+        zone: C1,
+        steps: [C1, D1, D1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Salamanca"],
+        capital: "Salamanca",
+        altitude: 770.0,
+        zone: D2,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Santa Cruz de Tenerife"],
+        capital: "Santa Cruz de Tenerife",
+        altitude: 0.0,
+        zone: Alfa3c,
+        steps: [A2c, A2c, A1c, A1c, B1c],
+    },
+    ProvinceClimateZones {
+        names: &["Cantabria"],
+        capital: "Santander",
+        altitude: 1.0,
+        zone: C1,
+        steps: [C1, D1, D1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Segovia"],
+        capital: "Segovia",
+        altitude: 1013.0,
+        zone: D2,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Sevilla"],
+        capital: "Sevilla",
+        altitude: 9.0,
+        zone: B4,
+        steps: [B3, C2, C1, D1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Soria"],
+        capital: "Soria",
+        altitude: 984.0,
+        zone: E1,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Tarragona"],
+        capital: "Tarragona",
+        altitude: 1.0,
+        zone: B3,
+        steps: [C2, C1, D1, D1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Teruel"],
+        capital: "Teruel",
+        altitude: 995.0,
+        zone: D2,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Toledo"],
+        capital: "Toledo",
+        altitude: 445.0,
+        zone: C4,
+        steps: [D3, D2, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Valencia", "València"],
+        capital: "Valencia",
+        altitude: 8.0,
+        zone: B3,
+        steps: [C2, C1, D1, D1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Valladolid"],
+        capital: "Valladolid",
+        altitude: 704.0,
+        zone: D2,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Álava", "Araba"],
+        capital: "Vitoria-Gasteiz",
+        altitude: 512.0,
+        zone: D1,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Zamora"],
+        capital: "Zamora",
+        altitude: 617.0,
+        zone: D2,
+        steps: [E1, E1, E1, E1, E1],
+    },
+    ProvinceClimateZones {
+        names: &["Zaragoza"],
+        capital: "Zaragoza",
+        altitude: 207.0,
+        zone: D3,
+        steps: [D2, E1, E1, E1, E1],
+    },
+];
+
+/// Nombre en minúsculas y sin tildes, para comparar denominaciones de provincias
+fn normalize_name(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' => 'a',
+            'é' | 'è' => 'e',
+            'í' | 'ì' | 'ï' => 'i',
+            'ó' | 'ò' => 'o',
+            'ú' | 'ù' | 'ü' => 'u',
+            c => c,
+        })
+        .collect()
+}
+
 /// Array de (20 climas canarios y 12 climas peninsulares) * 9 orientaciones (N, S, E, W, NE, NW, SE, SW, HZ) con datos de radiación mensual
 /// Estos datos nos permiten calcular de forma aproximada q_soljul
 pub static RADDATA: [SurfaceMonthlyRadiation; 288] = [
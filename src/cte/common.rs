@@ -2,7 +2,12 @@
 // Distributed under the MIT License
 // (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
 
-use std::{convert::TryFrom, error::Error, fmt::Display};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    error::Error,
+    fmt::Display,
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -68,7 +73,7 @@ impl Default for SpaceType {
 }
 
 /// Posiciones de los cerramientos según su inclinación
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Tilt {
     /// Suelo (inclinación < 60º)
     BOTTOM,
@@ -200,6 +205,72 @@ impl From<&str> for Orientation {
     }
 }
 
+/// Método de cálculo de la transmitancia térmica de los huecos
+///
+/// Solo se aplica al generar el modelo desde el .ctehexml. Las construcciones de hueco que se modifican o
+/// incorporan después (escenarios paramétricos y por lotes) usan el método de HULC.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum WindowUMethod {
+    /// Media ponderada por superficie de marco y vidrio incrementada en el porcentaje deltau (como HULC)
+    #[default]
+    HULC,
+    /// UNE-EN ISO 10077-1 con la geometría de cada hueco y la transmitancia lineal del intercalario, psi_g (W/mK)
+    ISO10077 { psi_g: f32 },
+}
+
+/// Método de generación de los id de los elementos del modelo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum IdScheme {
+    /// UUIDv5 a partir del espacio de nombres del proyecto, el tipo de elemento y su nombre
+    #[default]
+    Named,
+    /// UUID a partir del hash MD5 de la representación Debug del objeto BDL (versiones anteriores)
+    Legacy,
+}
+
+/// Tipo de elemento del modelo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ElementKind {
+    /// Espacio
+    Space,
+    /// Opaco
+    Wall,
+    /// Hueco
+    Window,
+    /// Puente térmico
+    ThermalBridge,
+    /// Construcción de opaco
+    WallCons,
+    /// Construcción de hueco
+    WindowCons,
+}
+
+impl Display for ElementKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let printable = match *self {
+            ElementKind::Space => "Space",
+            ElementKind::Wall => "Wall",
+            ElementKind::Window => "Window",
+            ElementKind::ThermalBridge => "ThermalBridge",
+            ElementKind::WallCons => "WallCons",
+            ElementKind::WindowCons => "WindowCons",
+        };
+        write!(f, "{}", printable)
+    }
+}
+
+/// Tratamiento de los elementos que dependen de un elemento eliminado
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RemovePolicy {
+    /// No se elimina el elemento si otros elementos hacen referencia a él
+    #[default]
+    Reject,
+    /// Se eliminan también los elementos que dependen de él (opacos de un espacio o con una
+    /// construcción, huecos de un opaco o con una construcción). Los opacos de otros espacios
+    /// adyacentes a un espacio eliminado se conservan, en contacto con el exterior
+    Cascade,
+}
+
 /// Datos mensuales de radiación por superficie
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SurfaceMonthlyRadiation {
@@ -209,7 +280,7 @@ pub struct SurfaceMonthlyRadiation {
     pub orientation: Orientation,
     /// Inclinación (Horiz=0, vertical=90)
     pub beta: f32,
-    /// Orientación (N=0, E=-90, W=+90, S=180)
+    /// Orientación (S=0, E=-90, W=+90, N=180)
     /// TODO: convertir a orientación UNE-EN ISO 52016-1, medido desde el sur, positivo al este, negativo al oeste (S=0, E=+90, W=-90)
     pub gamma: f32,
     /// Radiación mensual directa
@@ -226,6 +297,33 @@ pub struct SurfaceMonthlyRadiation {
     pub f_shwith500: [f32; 12],
 }
 
+/// Condiciones exteriores medias mensuales de una zona climática
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthlyClimate {
+    /// Zona climática
+    pub zone: ClimateZone,
+    /// Temperatura exterior media mensual (ºC)
+    pub temp: [f32; 12],
+    /// Humedad relativa exterior media mensual (%)
+    pub rh: [f32; 12],
+}
+
+/// Zonas climáticas de una provincia según el desnivel con su capital (DB-HE Anejo B, tabla B.1)
+#[derive(Debug, Clone, Copy)]
+pub struct ProvinceClimateZones {
+    /// Denominaciones de la provincia (p.e. Vizcaya, Bizkaia)
+    pub names: &'static [&'static str],
+    /// Capital de provincia
+    pub capital: &'static str,
+    /// Altitud de referencia de la capital (m)
+    pub altitude: f32,
+    /// Zona climática de la capital, y de las localidades con desnivel inferior a 200 m
+    pub zone: ClimateZone,
+    /// Zonas climáticas para desniveles con la capital de 200 a 400, 400 a 600, 600 a 800,
+    /// 800 a 1000 y 1000 m o más
+    pub steps: [ClimateZone; 5],
+}
+
 /// Nombres para la orientación de un elemento, según los puntos cardinales
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum ClimateZone {
@@ -445,6 +543,476 @@ pub struct KDetail {
     pub thermal_bridges_psi_l: f32,
 }
 
+/// Componentes del coeficiente de transferencia de calor por transmisión (W/K)
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct HtrParts {
+    /// Coeficiente de transferencia de calor por transmisión, H_tr = H_D + H_g + H_U + H_tb (W/K)
+    pub h_tr: f32,
+    /// Transmisión directa al exterior, H_D (W/K)
+    pub h_d: f32,
+    /// Transmisión al terreno, H_g (W/K)
+    pub h_g: f32,
+    /// Transmisión a través de espacios no acondicionados, H_U (W/K)
+    pub h_u: f32,
+    /// Transmisión por puentes térmicos, H_tb (W/K)
+    pub h_tb: f32,
+}
+
+/// Reporte del coeficiente de transferencia de calor por transmisión (UNE-EN ISO 13789)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct HtrDetail {
+    /// Valores del edificio
+    pub total: HtrParts,
+    /// Valores por espacio (id del espacio)
+    pub by_space: BTreeMap<String, HtrParts>,
+    /// Valores por zona térmica (condiciones operacionales de los espacios)
+    pub by_zone: BTreeMap<String, HtrParts>,
+}
+
+/// Componentes del coeficiente de transferencia de calor por ventilación
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct HveParts {
+    /// Volumen neto (m3)
+    pub volume: f32,
+    /// Caudal de ventilación, q_ve (m3/h)
+    pub q_ve: f32,
+    /// Caudal de infiltraciones, q_inf (m3/h)
+    pub q_inf: f32,
+    /// Coeficiente de transferencia de calor por ventilación, H_ve = 0.33 · (q_ve + q_inf) (W/K)
+    pub h_ve: f32,
+}
+
+/// Reporte del coeficiente de transferencia de calor por ventilación (UNE-EN ISO 13789)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct HveDetail {
+    /// Tasa de renovación de aire a 50Pa, n50 (1/h)
+    pub n50: f32,
+    /// Tasa de renovación de aire por infiltraciones, n_inf = n50 / 20 (1/h)
+    pub n_inf: f32,
+    /// Valores del edificio
+    pub total: HveParts,
+    /// Valores por espacio (id del espacio)
+    pub by_space: BTreeMap<String, HveParts>,
+    /// Valores por zona térmica (condiciones operacionales de los espacios)
+    pub by_zone: BTreeMap<String, HveParts>,
+}
+
+/// Pérdidas por transmisión de un elemento en la carga térmica de diseño (UNE-EN 12831-1)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct HeatLoadElement {
+    /// ID del opaco o hueco
+    pub id: String,
+    /// Nombre del opaco o hueco
+    pub name: String,
+    /// Superficie (m2)
+    pub area: f32,
+    /// Transmitancia térmica (W/m2K)
+    pub u: f32,
+    /// Factor de corrección de temperatura, f = (θ_int - θ_adj) / (θ_int - θ_e) (-)
+    pub f: f32,
+    /// Temperatura al otro lado del elemento, θ_adj (ºC)
+    pub theta_adj: f32,
+    /// Pérdidas de calor por transmisión, Φ = A·U·f·(θ_int - θ_e) (W)
+    pub phi: f32,
+}
+
+/// Carga térmica de diseño de un espacio (UNE-EN 12831-1)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SpaceHeatLoad {
+    /// ID del espacio
+    pub id: String,
+    /// Nombre del espacio
+    pub name: String,
+    /// Temperatura interior de diseño, θ_int (ºC)
+    pub theta_int: f32,
+    /// Pérdidas por transmisión, Φ_T (W)
+    pub phi_t: f32,
+    /// Pérdidas por ventilación e infiltraciones, Φ_V (W)
+    pub phi_v: f32,
+    /// Potencia de recuperación, Φ_RH (W)
+    pub phi_rh: f32,
+    /// Carga térmica de diseño, Φ_HL = Φ_T + Φ_V + Φ_RH (W)
+    pub phi_hl: f32,
+    /// Pérdidas por transmisión de cada elemento
+    pub elements: Vec<HeatLoadElement>,
+}
+
+/// Reporte de carga térmica de diseño de calefacción (UNE-EN 12831-1)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct HeatLoadDetail {
+    /// Temperatura exterior de diseño, θ_e (ºC)
+    pub theta_e: f32,
+    /// Temperatura exterior media anual, θ_m,e (ºC)
+    pub theta_m_e: f32,
+    /// Carga térmica de diseño del edificio, considerando los multiplicadores de los espacios (W)
+    pub phi_hl: f32,
+    /// Carga térmica de diseño de los espacios acondicionados (sin multiplicadores)
+    pub spaces: Vec<SpaceHeatLoad>,
+}
+
+/// Componentes de la carga sensible de refrigeración de una hora (W)
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct CoolingLoadComponents {
+    /// Ganancias solares por huecos
+    pub solar: f32,
+    /// Transmisión por huecos
+    pub windows: f32,
+    /// Transmisión por opacos al exterior (temperatura sol-aire)
+    pub walls: f32,
+    /// Cargas internas
+    pub internal: f32,
+    /// Ventilación e infiltraciones
+    pub ventilation: f32,
+    /// Carga total
+    pub total: f32,
+}
+
+/// Carga de refrigeración de diseño de un espacio (sin multiplicador)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SpaceCoolingLoad {
+    /// ID del espacio
+    pub id: String,
+    /// Nombre del espacio
+    pub name: String,
+    /// Hora solar de la carga punta (0-23)
+    pub peak_hour: u32,
+    /// Componentes de la carga punta
+    pub peak: CoolingLoadComponents,
+    /// Componentes horarios de la carga
+    pub hourly: Vec<CoolingLoadComponents>,
+}
+
+/// Reporte de carga de refrigeración de diseño (día de diseño de verano)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CoolingLoadDetail {
+    /// Temperatura exterior máxima del día de diseño (ºC)
+    pub theta_max: f32,
+    /// Oscilación diaria de la temperatura exterior (K)
+    pub daily_range: f32,
+    /// Temperatura interior de diseño (ºC)
+    pub theta_int: f32,
+    /// Hora solar de la carga punta del edificio (0-23)
+    pub peak_hour: u32,
+    /// Carga punta del edificio, considerando los multiplicadores de los espacios (W)
+    pub peak: f32,
+    /// Carga de refrigeración de los espacios acondicionados
+    pub spaces: Vec<SpaceCoolingLoad>,
+}
+
+/// Valor antes y después de una modificación del modelo
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ValueDelta {
+    /// Nombre del elemento o indicador
+    pub name: String,
+    /// Valor inicial
+    pub before: f32,
+    /// Valor final
+    pub after: f32,
+    /// Diferencia (final - inicial)
+    pub delta: f32,
+}
+
+impl ValueDelta {
+    /// Nueva diferencia entre valores
+    pub fn new(name: &str, before: f32, after: f32) -> Self {
+        ValueDelta {
+            name: name.to_string(),
+            before,
+            after,
+            delta: after - before,
+        }
+    }
+}
+
+/// Reporte de diferencias de un escenario de modificación de construcciones
+#[allow(non_snake_case)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ScenarioReport {
+    /// Transmitancia térmica global, K (W/m2K)
+    pub K: ValueDelta,
+    /// Parámetro de control solar, q_sol;jul (kWh/m2·mes)
+    pub q_soljul: ValueDelta,
+    /// Relación de cambio de aire a 50Pa, n50 (1/h)
+    pub n50: ValueDelta,
+    /// Resistencia intrínseca de las construcciones de opacos modificadas (m2K/W)
+    pub wallcons: Vec<ValueDelta>,
+    /// Transmitancia térmica de las construcciones de huecos modificadas (W/m2K)
+    pub wincons: Vec<ValueDelta>,
+    /// Transmitancia térmica de los opacos que cambian (W/m2K)
+    pub walls: Vec<ValueDelta>,
+}
+
+/// Diferencia en una propiedad de un elemento
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FieldDiff {
+    /// Nombre de la propiedad
+    pub field: String,
+    /// Valor inicial
+    pub before: String,
+    /// Valor final
+    pub after: String,
+}
+
+/// Elemento añadido, eliminado o modificado entre dos versiones de un modelo
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ElementDiff {
+    /// Tipo de elemento (Space, Wall, Window, ThermalBridge, WallCons, WindowCons)
+    pub kind: String,
+    /// Id del elemento (en la versión final, salvo para los eliminados)
+    pub id: String,
+    /// Nombre del elemento (en la versión final, salvo para los eliminados)
+    pub name: String,
+    /// Diferencias en las propiedades del elemento (solo en los modificados)
+    pub changes: Vec<FieldDiff>,
+}
+
+impl ElementDiff {
+    /// Nuevo elemento sin diferencias en sus propiedades
+    pub fn new(kind: &str, id: &str, name: &str) -> Self {
+        ElementDiff {
+            kind: kind.to_string(),
+            id: id.to_string(),
+            name: name.to_string(),
+            changes: Vec::new(),
+        }
+    }
+}
+
+/// Reporte de diferencias entre dos versiones de un modelo
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ModelDiff {
+    /// Elementos añadidos
+    pub added: Vec<ElementDiff>,
+    /// Elementos eliminados
+    pub removed: Vec<ElementDiff>,
+    /// Elementos modificados
+    pub changed: Vec<ElementDiff>,
+    /// Variación de los indicadores (K y su desglose, q_sol;jul, n50, compacidad, volumen y superficie de referencia)
+    pub indicators: Vec<ValueDelta>,
+}
+
+/// Resultado de una combinación de valores de un análisis por lotes
+#[allow(non_snake_case)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// Número de combinación
+    pub id: usize,
+    /// Valores de los parámetros
+    pub values: Vec<String>,
+    /// Transmitancia térmica global, K (W/m2K)
+    pub K: f32,
+    /// Transmitancia térmica global límite, K_lim (W/m2K)
+    pub K_lim: f32,
+    /// Parámetro de control solar, q_sol;jul (kWh/m2·mes)
+    pub q_soljul: f32,
+    /// Parámetro de control solar límite, q_sol;jul,lim (kWh/m2·mes)
+    pub q_soljul_lim: f32,
+    /// Relación de cambio de aire a 50Pa, n50 (1/h)
+    pub n50: f32,
+    /// Relación de cambio de aire a 50Pa límite, n50_lim (1/h), si es de aplicación
+    pub n50_lim: Option<f32>,
+    /// Compacidad, V/A (m3/m2)
+    pub compacity: f32,
+    /// ¿Cumple K <= K_lim?
+    pub K_ok: bool,
+    /// ¿Cumple q_sol;jul <= q_sol;jul,lim?
+    pub q_soljul_ok: bool,
+    /// ¿Cumple n50 <= n50_lim?
+    pub n50_ok: bool,
+    /// ¿Cumple todos los límites?
+    pub ok: bool,
+}
+
+/// Aislamiento añadido a una construcción de opaco
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct InsulationChange {
+    /// Nombre de la construcción de opaco
+    pub wallcons: String,
+    /// Material aislante
+    pub material: String,
+    /// Espesor añadido (m)
+    pub thickness: f32,
+    /// Superficie de los opacos con la construcción, incluyendo multiplicadores (m2)
+    pub area: f32,
+    /// Resistencia térmica añadida (m2K/W)
+    pub r_added: f32,
+    /// Coste del aislamiento añadido (€), si se indica el coste del material
+    pub cost: Option<f32>,
+}
+
+/// Resultado de la optimización del espesor de aislamiento
+///
+/// Cada construcción indica el material aislante candidato elegido para ella
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct InsulationPlan {
+    /// Transmitancia térmica global inicial (W/m2K)
+    pub k_before: f32,
+    /// Transmitancia térmica global con el aislamiento añadido (W/m2K)
+    pub k_after: f32,
+    /// Transmitancia térmica global objetivo (W/m2K)
+    pub k_target: Option<f32>,
+    /// ¿Se alcanzan los objetivos con el espesor máximo?
+    pub feasible: bool,
+    /// Coste total (€), si se indica el coste del material
+    pub total_cost: Option<f32>,
+    /// Aislamiento añadido a cada construcción
+    pub changes: Vec<InsulationChange>,
+}
+
+/// Reporte de ganancias solares mensuales a través de la envolvente (kWh/mes)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SolarGainsDetail {
+    /// Ganancias solares totales
+    pub total: [f32; 12],
+    /// Ganancias solares a través de los huecos
+    pub windows: [f32; 12],
+    /// Ganancias solares a través de los opacos
+    pub walls: [f32; 12],
+    /// Ganancias solares por orientación
+    pub by_orientation: HashMap<Orientation, [f32; 12]>,
+    /// Ganancias solares por espacio (id del espacio)
+    pub by_space: BTreeMap<String, [f32; 12]>,
+}
+
+/// Factor de reducción de temperatura de un espacio no acondicionado (UNE-EN ISO 13789:2017 6.4)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BtrDetail {
+    /// ID del espacio
+    pub space: String,
+    /// Nombre del espacio
+    pub name: String,
+    /// Coeficiente de transmisión de calor de los espacios acondicionados al espacio, H_iu (W/K)
+    pub h_iu: f32,
+    /// Transmisión de calor por opacos y huecos al exterior o al terreno, Σ A·U (W/K)
+    pub ua_ue: f32,
+    /// Transmisión de calor por ventilación, H_ve = 0.33·n·V (W/K)
+    pub h_ve: f32,
+    /// Coeficiente de transmisión de calor del espacio al exterior, H_ue = Σ A·U + H_ve (W/K)
+    pub h_ue: f32,
+    /// Tasa de renovación de aire del espacio, n (ren/h)
+    pub n_v: f32,
+    /// Volumen neto del espacio, V (m3)
+    pub volume: f32,
+    /// Factor de reducción de temperatura, b_tr = H_ue / (H_iu + H_ue) (-)
+    pub btr: f32,
+}
+
+/// Flujo de calor mensual a través del terreno de un elemento (UNE-EN ISO 13370:2017 Anexo A)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GroundElementHeatFlow {
+    /// ID del elemento
+    pub id: String,
+    /// Nombre del elemento
+    pub name: String,
+    /// Espacio al que pertenece el elemento
+    pub space: String,
+    /// Coeficiente de transmisión estacionario, H_g (W/K)
+    pub h_g: f32,
+    /// Coeficiente de transmisión periódico interior, H_pi (W/K)
+    pub h_pi: f32,
+    /// Coeficiente de transmisión periódico exterior, H_pe (W/K)
+    pub h_pe: f32,
+    /// Desfase del flujo de calor respecto a la temperatura interior, α (meses)
+    pub alpha: f32,
+    /// Desfase del flujo de calor respecto a la temperatura exterior, β (meses)
+    pub beta: f32,
+    /// Flujo de calor medio mensual hacia el terreno (W)
+    pub flow: [f32; 12],
+}
+
+/// Reporte de flujo de calor mensual a través del terreno (UNE-EN ISO 13370:2017 Anexo A)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GroundHeatFlowDetail {
+    /// Profundidad de penetración periódica del terreno, δ (m)
+    pub delta: f32,
+    /// Temperatura interior media anual, θ̄_i (ºC)
+    pub theta_i_mean: f32,
+    /// Amplitud de la variación de la temperatura interior, θ̂_i (ºC)
+    pub theta_i_amplitude: f32,
+    /// Temperatura exterior media anual, θ̄_e (ºC)
+    pub theta_e_mean: f32,
+    /// Amplitud de la variación de la temperatura exterior, θ̂_e (ºC)
+    pub theta_e_amplitude: f32,
+    /// Mes con la temperatura exterior mínima, τ [1-12]
+    pub tau: u32,
+    /// Flujo de calor por elemento
+    pub elements: Vec<GroundElementHeatFlow>,
+    /// Flujo de calor mensual hacia el terreno por espacio (id del espacio) (W)
+    pub by_space: BTreeMap<String, [f32; 12]>,
+    /// Flujo de calor mensual total hacia el terreno (W)
+    pub total: [f32; 12],
+}
+
+/// Reporte de comprobación de condensaciones superficiales (DB-HE2 y UNE-EN ISO 13788)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SurfaceCondensationDetail {
+    /// Clase de higrometría usada en la comprobación
+    pub hygrometry: u8,
+    /// Factor de temperatura de la superficie interior mínimo, fRsi,min (-)
+    pub f_rsi_min: f32,
+    /// Mes crítico (0 -> enero, 11 -> diciembre), si en algún mes existe riesgo de condensación
+    pub critical_month: Option<usize>,
+    /// Factor de temperatura de la superficie interior mínimo de cada mes (-)
+    pub monthly_f_rsi_min: [f32; 12],
+    /// Comprobación de opacos y puentes térmicos
+    pub elements: Vec<FRsiCheck>,
+}
+
+/// Comprobación del factor de temperatura de la superficie interior de un elemento
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FRsiCheck {
+    /// Id del elemento (opaco o puente térmico)
+    pub id: String,
+    /// Nombre del elemento
+    pub name: String,
+    /// ¿Es un puente térmico?
+    pub is_thermal_bridge: bool,
+    /// Factor de temperatura de la superficie interior del elemento, fRsi (-)
+    pub f_rsi: f32,
+    /// ¿Cumple fRsi >= fRsi,min?
+    pub ok: bool,
+}
+
+/// Reporte de comprobación de condensaciones intersticiales de una construcción (método de Glaser, UNE-EN ISO 13788)
+///
+/// Los perfiles se dan en las interfases entre capas, desde la superficie exterior (primera) a la interior (última)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GlaserDetail {
+    /// Nombre de la construcción
+    pub cons: String,
+    /// Posición del elemento usada para fijar las resistencias superficiales
+    pub position: Option<Tilt>,
+    /// Nombres de las capas, de exterior a interior
+    pub layers: Vec<String>,
+    /// Espesor de aire equivalente acumulado desde el exterior en cada interfase, s_d (m)
+    pub s_d: Vec<f32>,
+    /// Resultados mensuales (de enero a diciembre)
+    pub monthly: Vec<GlaserMonth>,
+    /// ¿Se producen condensaciones en algún mes?
+    pub condensation: bool,
+    /// Cantidad máxima de agua acumulada en el conjunto de interfases a lo largo del año (kg/m²)
+    pub max_accumulated: f32,
+    /// ¿Se evapora toda el agua condensada a lo largo del año?
+    pub dries_out: bool,
+}
+
+/// Resultados mensuales del método de Glaser en las interfases de una construcción
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GlaserMonth {
+    /// Temperatura (ºC)
+    pub temp: Vec<f32>,
+    /// Presión de saturación del vapor (Pa)
+    pub p_sat: Vec<f32>,
+    /// Presión de vapor (Pa)
+    pub p: Vec<f32>,
+    /// Índices de las interfases con condensación o con agua acumulada (planos de condensación)
+    pub planes: Vec<usize>,
+    /// Cantidad de agua condensada (+) o evaporada (-) en el mes (kg/m²)
+    pub g_c: Vec<f32>,
+    /// Cantidad de agua acumulada al final del mes (kg/m²)
+    pub accumulated: Vec<f32>,
+}
+
 /// Reporte de cálculo de n50_he2019
 #[allow(non_snake_case)]
 #[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
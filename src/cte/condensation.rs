@@ -0,0 +1,420 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Comprobación de condensaciones en los elementos de la envolvente
+//! - UNE-EN ISO 13788:2016 para el cálculo de condensaciones superficiales e intersticiales (método de Glaser)
+//! - DA DB-HE/2 para las condiciones interiores según la clase de higrometría
+
+use std::collections::HashMap;
+
+use anyhow::{format_err, Error};
+use log::{debug, info, warn};
+
+use super::{
+    model_impl::{RSE, RSI_ASCENDENTE, RSI_DESCENDENTE, RSI_HORIZONTAL},
+    BoundaryType, FRsiCheck, GlaserDetail, GlaserMonth, Model, MonthlyClimate,
+    SurfaceCondensationDetail, Tilt, UValues,
+};
+use crate::bdl;
+
+/// Temperatura interior de cálculo (ºC)
+pub const TEMP_INT: f32 = 20.0;
+/// Resistencia superficial interior para la comprobación de condensaciones superficiales [m2·K/W]
+const RSI_CONDENSATION: f32 = 0.25;
+/// Humedad relativa superficial máxima para evitar el crecimiento de moho [-]
+const PHI_SI_MAX: f32 = 0.8;
+/// Permeabilidad al vapor de agua del aire [kg/(m·s·Pa)]
+const DELTA_0: f32 = 2.0e-10;
+/// Días de cada mes
+const DAYS_IN_MONTH: [f32; 12] = [
+    31.0, 28.0, 31.0, 30.0, 31.0, 30.0, 31.0, 31.0, 30.0, 31.0, 30.0, 31.0,
+];
+
+/// Presión de saturación del vapor de agua (Pa) a la temperatura indicada (ºC)
+/// UNE-EN ISO 13788:2016, ecuaciones (E.7) y (E.8)
+pub fn p_sat(temp: f32) -> f32 {
+    if temp >= 0.0 {
+        610.5 * f32::exp(17.269 * temp / (237.3 + temp))
+    } else {
+        610.5 * f32::exp(21.875 * temp / (265.5 + temp))
+    }
+}
+
+/// Temperatura (ºC) para la que la presión de saturación del vapor de agua es p (Pa)
+/// Inversa de p_sat
+pub fn temp_for_p_sat(p: f32) -> f32 {
+    let x = f32::ln(p / 610.5);
+    if p >= 610.5 {
+        237.3 * x / (17.269 - x)
+    } else {
+        265.5 * x / (21.875 - x)
+    }
+}
+
+/// Humedad relativa interior (%) según la clase de higrometría del espacio (DA DB-HE/2)
+/// - clase 5: 70%
+/// - clase 4: 62%
+/// - clase 3 o inferior: 55%
+pub fn rh_int_for_hygrometry(hygrometry: u8) -> f32 {
+    match hygrometry {
+        5 => 70.0,
+        4 => 62.0,
+        _ => 55.0,
+    }
+}
+
+/// Factor de temperatura de la superficie interior mínimo de cada mes, fRsi,min (-)
+///
+/// Se obtiene la temperatura superficial mínima que mantiene la humedad relativa superficial
+/// por debajo del 80% (crecimiento de moho) con las condiciones interiores de la clase de higrometría.
+/// Los meses sin riesgo de condensación tienen un valor nulo
+pub fn monthly_f_rsi_min(climate: &MonthlyClimate, hygrometry: u8) -> [f32; 12] {
+    let p_i = 0.01 * rh_int_for_hygrometry(hygrometry) * p_sat(TEMP_INT);
+    let temp_si_min = temp_for_p_sat(p_i / PHI_SI_MAX);
+    let mut f_rsi_min = [0.0; 12];
+    for (f, temp_e) in f_rsi_min.iter_mut().zip(climate.temp.iter()) {
+        if *temp_e < temp_si_min {
+            *f = (temp_si_min - temp_e) / (TEMP_INT - temp_e);
+        }
+    }
+    f_rsi_min
+}
+
+impl Model {
+    /// Comprobación de condensaciones superficiales según DB-HE2 con las condiciones exteriores indicadas
+    ///
+    /// Compara el factor de temperatura de la superficie interior (fRsi) de los opacos y puentes térmicos
+    /// de la envolvente con el valor mínimo (fRsi,min) del mes más desfavorable.
+    /// Notas:
+    /// - en opacos, fRsi = 1 - 0.25·U
+    /// - solo se comprueban los opacos en contacto con el aire exterior (el terreno limita la condensación superficial)
+    /// - se ignoran los puentes térmicos sin fRsi definido y los opacos sin U calculable
+    pub fn surface_condensation(&self, climate: &MonthlyClimate) -> SurfaceCondensationDetail {
+        self.surface_condensation_with(climate, &self.u_values())
+    }
+
+    /// surface_condensation con las transmitancias térmicas ya calculadas (ver Model::u_values)
+    pub fn surface_condensation_with(
+        &self,
+        climate: &MonthlyClimate,
+        u_values: &UValues,
+    ) -> SurfaceCondensationDetail {
+        let hygrometry = self.meta.hygrometry;
+        let monthly_f_rsi_min = monthly_f_rsi_min(climate, hygrometry);
+        let (critical_month, f_rsi_min) =
+            monthly_f_rsi_min
+                .iter()
+                .enumerate()
+                .fold((None, 0.0_f32), |(acc_m, acc_f), (m, f)| {
+                    if *f > acc_f {
+                        (Some(m), *f)
+                    } else {
+                        (acc_m, acc_f)
+                    }
+                });
+
+        let walls = self
+            .walls_of_envelope_with(&u_values.index)
+            .filter(|w| w.bounds == BoundaryType::EXTERIOR)
+            .filter_map(|w| {
+                let u = u_values.wall(w)?;
+                let f_rsi = 1.0 - RSI_CONDENSATION * u;
+                debug!("{} fRsi={:.2} (U={:.2})", w.name, f_rsi, u);
+                Some(FRsiCheck {
+                    id: w.id.clone(),
+                    name: w.name.clone(),
+                    is_thermal_bridge: false,
+                    f_rsi,
+                    ok: f_rsi >= f_rsi_min,
+                })
+            });
+        let thermal_bridges = self.thermal_bridges.iter().filter_map(|tb| {
+            let f_rsi = tb.frsi?;
+            Some(FRsiCheck {
+                id: tb.id.clone(),
+                name: tb.name.clone(),
+                is_thermal_bridge: true,
+                f_rsi,
+                ok: f_rsi >= f_rsi_min,
+            })
+        });
+        let elements: Vec<FRsiCheck> = walls.chain(thermal_bridges).collect();
+
+        info!(
+            "fRsi,min={:.2} (mes crítico: {}, clase de higrometría: {}), elementos con riesgo de condensación: {}",
+            f_rsi_min,
+            critical_month.map(|m| (m + 1).to_string()).unwrap_or_else(|| "-".to_string()),
+            hygrometry,
+            elements.iter().filter(|e| !e.ok).count()
+        );
+
+        SurfaceCondensationDetail {
+            hygrometry,
+            f_rsi_min,
+            critical_month,
+            monthly_f_rsi_min,
+            elements,
+        }
+    }
+}
+
+/// Comprobación de condensaciones intersticiales de una composición de capas (método de Glaser)
+///
+/// Sigue el procedimiento mensual de la UNE-EN ISO 13788:2016 (apartado 6):
+/// - la primera capa de la composición es la exterior y la última la interior
+/// - las condiciones interiores son 20ºC y la humedad relativa de la clase de higrometría
+/// - el cálculo comienza en el primer mes con condensación tras un mes sin ella y recorre un año completo,
+///   acumulando el agua condensada en cada interfase y evaporándola en los meses siguientes
+/// - los materiales sin factor de resistencia a la difusión del vapor (cámaras de aire, definición por resistencia) se
+///   consideran con mu = 1
+pub fn glaser(
+    cons: &bdl::WallCons,
+    materialsdb: &HashMap<String, bdl::Material>,
+    position: Tilt,
+    climate: &MonthlyClimate,
+    hygrometry: u8,
+) -> Result<GlaserDetail, Error> {
+    let (rsi, rse) = match position {
+        Tilt::TOP => (RSI_ASCENDENTE, RSE),
+        Tilt::BOTTOM => (RSI_DESCENDENTE, RSE),
+        Tilt::SIDE => (RSI_HORIZONTAL, RSE),
+    };
+
+    // Resistencia térmica y espesor de aire equivalente de cada capa
+    let layers = cons
+        .material
+        .iter()
+        .zip(&cons.thickness)
+        .map(|(name, thk)| {
+            let mat = materialsdb.get(name).ok_or_else(|| {
+                format_err!(
+                    "No se encuentra el material \"{}\" de la composición de capas \"{}\"",
+                    name,
+                    cons.name
+                )
+            })?;
+            match (mat.properties, mat.resistance) {
+                (Some(props), _) if props.conductivity != 0.0 => {
+                    let mu = match props.vapourdiffusivity {
+                        Some(mu) if mu > 0.0 => mu,
+                        _ => 1.0,
+                    };
+                    Ok((thk / props.conductivity, mu * thk))
+                }
+                (None, Some(r)) => Ok((r, *thk)),
+                _ => Err(format_err!(
+                    "Material \"{}\" no definido por conductividad o resistencia",
+                    name
+                )),
+            }
+        })
+        .collect::<Result<Vec<(f32, f32)>, Error>>()?;
+
+    // Resistencias térmicas y espesores de aire equivalente acumulados desde el exterior en cada interfase
+    let mut r_acc = vec![rse];
+    let mut s_d = vec![0.0];
+    for (r, sd) in &layers {
+        r_acc.push(r_acc.last().unwrap() + r);
+        s_d.push(s_d.last().unwrap() + sd);
+    }
+    let r_total = r_acc.last().unwrap() + rsi;
+    let n = s_d.len();
+
+    let p_i = 0.01 * rh_int_for_hygrometry(hygrometry) * p_sat(TEMP_INT);
+
+    // Perfiles de temperatura y presión de saturación en las interfases
+    let profiles = (0..12)
+        .map(|m| {
+            let temp_e = climate.temp[m];
+            let temp: Vec<f32> = r_acc
+                .iter()
+                .map(|r| temp_e + (TEMP_INT - temp_e) * r / r_total)
+                .collect();
+            let psat: Vec<f32> = temp.iter().map(|t| p_sat(*t)).collect();
+            let p_e = 0.01 * climate.rh[m] * p_sat(temp_e);
+            (temp, psat, p_e)
+        })
+        .collect::<Vec<_>>();
+
+    // Meses con condensación sin considerar el agua acumulada
+    let no_water = vec![0.0; n];
+    let has_condensation = profiles
+        .iter()
+        .map(|(_, psat, p_e)| {
+            let (_, planes, g) = glaser_month(&s_d, psat, *p_e, p_i, &no_water);
+            !planes.is_empty() && g.iter().any(|g| *g > 0.0)
+        })
+        .collect::<Vec<bool>>();
+
+    // Mes de comienzo: el primero con condensación tras un mes sin ella
+    let start = (0..12).find(|&m| has_condensation[m] && !has_condensation[(m + 11) % 12]);
+    let condensation = has_condensation.iter().any(|c| *c);
+
+    let mut monthly: Vec<GlaserMonth> = vec![Default::default(); 12];
+    let mut accumulated = vec![0.0_f32; n];
+    let mut max_accumulated = 0.0_f32;
+    for m in (0..12).map(|i| (i + start.unwrap_or(0)) % 12) {
+        let (temp, psat, p_e) = &profiles[m];
+        let (p, planes, flux) = glaser_month(&s_d, psat, *p_e, p_i, &accumulated);
+        let seconds = DAYS_IN_MONTH[m] * 24.0 * 3600.0;
+        let mut g_c = vec![0.0; n];
+        for j in &planes {
+            // No se puede evaporar más agua de la acumulada
+            g_c[*j] = f32::max(flux[*j] * seconds, -accumulated[*j]);
+            accumulated[*j] += g_c[*j];
+        }
+        max_accumulated = f32::max(max_accumulated, accumulated.iter().sum());
+        monthly[m] = GlaserMonth {
+            temp: temp.clone(),
+            p_sat: psat.clone(),
+            p,
+            planes,
+            g_c,
+            accumulated: accumulated.clone(),
+        };
+    }
+    let dries_out = accumulated.iter().all(|w| *w < 1e-6);
+
+    if condensation {
+        warn!(
+            "Condensaciones intersticiales en {}: agua acumulada máxima={:.4} kg/m², se evapora en el año: {}",
+            cons.name,
+            max_accumulated,
+            if dries_out { "sí" } else { "no" }
+        );
+    } else {
+        debug!("Sin condensaciones intersticiales en {}", cons.name);
+    }
+
+    Ok(GlaserDetail {
+        cons: cons.name.clone(),
+        position: Some(position),
+        layers: cons.material.clone(),
+        s_d,
+        monthly,
+        condensation,
+        max_accumulated,
+        dries_out,
+    })
+}
+
+/// Perfil de presión de vapor, planos de condensación y flujo neto de condensación en ellos [kg/(m²·s)]
+///
+/// La presión de vapor es lineal con el espesor de aire equivalente entre los puntos fijos: superficies exterior
+/// e interior, planos con agua acumulada y planos en los que la presión de vapor alcanzaría la de saturación.
+fn glaser_month(
+    s_d: &[f32],
+    psat: &[f32],
+    p_e: f32,
+    p_i: f32,
+    accumulated: &[f32],
+) -> (Vec<f32>, Vec<usize>, Vec<f32>) {
+    let n = s_d.len();
+    let last = n - 1;
+    // Planos con agua acumulada
+    let mut planes: Vec<usize> = (1..last).filter(|&j| accumulated[j] > 0.0).collect();
+
+    // Presión de vapor en un punto fijo
+    let fixed_p = |j: usize| {
+        if j == 0 {
+            p_e
+        } else if j == last {
+            p_i
+        } else {
+            psat[j]
+        }
+    };
+
+    let p = loop {
+        let mut fixed = planes.clone();
+        fixed.push(0);
+        fixed.push(last);
+        fixed.sort_unstable();
+        fixed.dedup();
+
+        // Interpolación lineal con s_d entre puntos fijos
+        let mut p = vec![0.0; n];
+        for pair in fixed.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let (p_a, p_b) = (fixed_p(a), fixed_p(b));
+            let ds = s_d[b] - s_d[a];
+            for (j, pj) in p.iter_mut().enumerate().take(b + 1).skip(a) {
+                *pj = if ds > 0.0 {
+                    p_a + (p_b - p_a) * (s_d[j] - s_d[a]) / ds
+                } else {
+                    p_a
+                };
+            }
+        }
+
+        // Plano con mayor sobrepresión respecto a la de saturación
+        let worst = (1..last)
+            .filter(|j| !planes.contains(j))
+            .map(|j| (j, p[j] - psat[j]))
+            .filter(|(_, excess)| *excess > 0.01)
+            .fold(None, |acc: Option<(usize, f32)>, (j, excess)| match acc {
+                Some((_, e)) if e >= excess => acc,
+                _ => Some((j, excess)),
+            });
+        match worst {
+            Some((j, _)) => planes.push(j),
+            None => break p,
+        }
+    };
+    planes.sort_unstable();
+
+    // Flujo neto en cada plano: el que llega desde el interior menos el que sale hacia el exterior
+    let mut flux = vec![0.0; n];
+    for &j in &planes {
+        let prev = (0..j)
+            .rev()
+            .find(|k| *k == 0 || planes.contains(k))
+            .unwrap();
+        let next = (j + 1..n)
+            .find(|k| *k == last || planes.contains(k))
+            .unwrap();
+        let g_in = DELTA_0 * (p[next] - p[j]) / f32::max(s_d[next] - s_d[j], 1e-6);
+        let g_out = DELTA_0 * (p[j] - p[prev]) / f32::max(s_d[j] - s_d[prev], 1e-6);
+        flux[j] = g_in - g_out;
+    }
+    (p, planes, flux)
+}
+
+impl Model {
+    /// Comprobación de condensaciones intersticiales de las construcciones de opacos en contacto con el aire exterior,
+    /// con las condiciones exteriores indicadas
+    ///
+    /// Se obtiene un resultado por construcción usada en opacos de la envolvente en contacto con el aire exterior,
+    /// usando la posición del primer opaco que la emplea.
+    /// Se ignoran las construcciones sin composición de capas en la base de datos
+    pub fn interstitial_condensation(
+        &self,
+        db: &bdl::DB,
+        climate: &MonthlyClimate,
+    ) -> Vec<GlaserDetail> {
+        let index = self.index();
+        self.wallcons
+            .iter()
+            .filter_map(|wc| {
+                let wall = self
+                    .walls_of_envelope_with(&index)
+                    .find(|w| w.bounds == BoundaryType::EXTERIOR && w.cons == wc.id)?;
+                let cons = db.wallcons.get(&wc.name)?;
+                match glaser(
+                    cons,
+                    &db.materials,
+                    Tilt::from(wall),
+                    climate,
+                    self.meta.hygrometry,
+                ) {
+                    Ok(detail) => Some(detail),
+                    Err(e) => {
+                        warn!("{}", e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
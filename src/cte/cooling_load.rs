@@ -0,0 +1,364 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Estimación simplificada de la carga de refrigeración de diseño por espacio
+//!
+//! Se calcula la carga sensible horaria de un día de diseño de verano (21 de julio, cielo despejado):
+//! - ganancias solares por huecos, con la radiación sobre cada hueco según su orientación,
+//!   el factor de obstáculos remotos y el factor solar con la protección solar móvil activada (g_gl;sh;wi)
+//!   cuando la irradiancia supera el nivel de activación
+//! - transmisión por huecos con la temperatura exterior horaria
+//! - transmisión por opacos al exterior con la temperatura sol-aire, amortiguada y desfasada según
+//!   las características dinámicas de la composición (UNE-EN ISO 13786)
+//! - cargas internas según las condiciones operacionales del espacio
+//! - ventilación e infiltraciones (ver Model::H_ve)
+//!
+//! La radiación se obtiene con el modelo de cielo despejado de ASHRAE y la variación horaria de la
+//! temperatura exterior con el perfil de oscilación diaria de ASHRAE.
+
+use std::collections::BTreeMap;
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    model_impl::RSE, shading::ACTIVATION_DEFAULT, BoundaryType, CoolingLoadComponents,
+    CoolingLoadDetail, Model, Space, SpaceCoolingLoad, SpaceType, UValues, Wall,
+};
+
+/// Temperatura interior de diseño de refrigeración (ºC)
+pub const THETA_INT_COOLING: f32 = 25.0;
+
+/// Fracción de la oscilación diaria que se resta a la temperatura máxima en cada hora (ASHRAE)
+const DAILY_RANGE_PROFILE: [f32; 24] = [
+    0.87, 0.92, 0.96, 0.99, 1.00, 0.98, 0.93, 0.84, 0.71, 0.56, 0.39, 0.23, 0.11, 0.03, 0.00, 0.03,
+    0.10, 0.21, 0.34, 0.47, 0.58, 0.68, 0.76, 0.82,
+];
+
+/// Declinación solar el 21 de julio (grados)
+const DECLINATION_JULY: f32 = 20.4;
+
+/// Coeficientes del modelo de cielo despejado de ASHRAE para julio (A en W/m2, B y C adimensionales)
+const ASHRAE_A: f32 = 1085.0;
+const ASHRAE_B: f32 = 0.207;
+const ASHRAE_C: f32 = 0.136;
+
+/// Reflectancia solar del terreno
+const ALBEDO: f32 = 0.2;
+
+/// Corrección de la temperatura sol-aire por radiación de onda larga hacia el cielo en
+/// superficies horizontales, ε·ΔR/h_e (K)
+const SKY_CORRECTION: f32 = 3.9;
+
+/// Cargas internas sensibles de un uso terciario durante sus horas de uso (W/m2)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TertiaryGains {
+    /// Ocupación sensible
+    pub occupancy: f32,
+    /// Iluminación
+    pub lighting: f32,
+    /// Equipos
+    pub equipment: f32,
+}
+
+/// Condiciones de diseño de refrigeración de la localidad y cargas internas de los usos terciarios
+///
+/// Las temperaturas se toman de los datos climáticos de proyecto de la localidad (p.e. temperatura
+/// seca del percentil 1% y oscilación media diaria de la guía técnica de condiciones climáticas
+/// exteriores de proyecto) y la latitud de su emplazamiento (p.e. MetData::latitude)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoolingDesignConditions {
+    /// Temperatura exterior máxima del día de diseño (ºC)
+    pub theta_max: f32,
+    /// Oscilación diaria de la temperatura exterior (K)
+    pub daily_range: f32,
+    /// Latitud del emplazamiento (grados)
+    pub latitude: f32,
+    /// Cargas internas de los usos terciarios por intensidad de uso (Baja, Media, Alta)
+    pub tertiary_gains: BTreeMap<String, TertiaryGains>,
+}
+
+/// Temperatura exterior horaria del día de diseño de refrigeración (ºC)
+pub fn design_day_temperatures(theta_max: f32, daily_range: f32) -> [f32; 24] {
+    let mut temps = [0.0; 24];
+    for (t, f) in temps.iter_mut().zip(DAILY_RANGE_PROFILE.iter()) {
+        *t = theta_max - f * daily_range;
+    }
+    temps
+}
+
+/// Irradiancia solar horaria de cielo despejado sobre una superficie el día de diseño (W/m2)
+///
+/// - latitude: latitud del emplazamiento (grados)
+/// - tilt: inclinación de la superficie (0 -> horizontal hacia arriba, 90 -> vertical)
+/// - azimuth: orientación de la superficie (S=0, E=+90, W=-90)
+///
+/// Se considera la hora solar en el punto medio de cada hora
+pub fn design_day_irradiance(latitude: f32, tilt: f32, azimuth: f32) -> [f32; 24] {
+    let (phi, decl) = (latitude.to_radians(), DECLINATION_JULY.to_radians());
+    let (beta, gamma) = (tilt.to_radians(), azimuth.to_radians());
+    let mut irradiance = [0.0; 24];
+    for (h, irr) in irradiance.iter_mut().enumerate() {
+        // Ángulo horario, negativo por la mañana
+        let omega = (15.0 * (h as f32 + 0.5 - 12.0)).to_radians();
+        let sin_alt = phi.sin() * decl.sin() + phi.cos() * decl.cos() * omega.cos();
+        if sin_alt <= 0.0 {
+            continue;
+        }
+        let cos_alt = (1.0 - sin_alt * sin_alt).sqrt();
+        // Azimut solar (S=0, E+, W-)
+        let sun_azimuth = -f32::atan2(
+            omega.sin(),
+            omega.cos() * phi.sin() - decl.tan() * phi.cos(),
+        );
+        let cos_inc = cos_alt * beta.sin() * (sun_azimuth - gamma).cos() + sin_alt * beta.cos();
+        let i_dn = ASHRAE_A * f32::exp(-ASHRAE_B / sin_alt);
+        let direct = i_dn * cos_inc.max(0.0);
+        let diffuse = ASHRAE_C * i_dn * (1.0 + beta.cos()) / 2.0;
+        let reflected = ALBEDO * i_dn * (ASHRAE_C + sin_alt) * (1.0 - beta.cos()) / 2.0;
+        *irr = direct + diffuse + reflected;
+    }
+    irradiance
+}
+
+/// Cargas internas sensibles horarias por unidad de superficie según las condiciones operacionales (W/m2)
+///
+/// - Residencial: perfil de uso residencial del DB-HE (ocupación sensible, iluminación y equipos)
+/// - Terciario (I_<Baja|Media|Alta>-<N>h-...): cargas de tertiary_gains según la intensidad de uso,
+///   durante las N horas de uso, desde las 8h
+/// - Otros (p.e. espacios no habitables) o intensidades sin cargas definidas: sin cargas internas
+pub fn internal_gains_profile(
+    conditions: Option<&str>,
+    tertiary_gains: &BTreeMap<String, TertiaryGains>,
+) -> [f32; 24] {
+    let mut gains = [0.0; 24];
+    let conditions = match conditions {
+        Some(c) => c,
+        None => return gains,
+    };
+    if conditions == "Residencial" {
+        for (h, g) in gains.iter_mut().enumerate() {
+            let occupancy = match h {
+                0..=6 | 23 => 2.15,
+                7..=14 => 0.54,
+                _ => 1.08,
+            };
+            let lighting_equipment = match h {
+                0..=6 => 0.44,
+                7..=16 => 1.32,
+                17 | 23 => 2.20,
+                _ => 4.40,
+            };
+            *g = occupancy + 2.0 * lighting_equipment;
+        }
+    } else if let Some(rest) = conditions.strip_prefix("I_") {
+        let mut parts = rest.split('-');
+        let intensity = parts.next().unwrap_or_default();
+        let tg = match tertiary_gains.get(intensity) {
+            Some(tg) => tg,
+            None => {
+                warn!(
+                    "Sin cargas internas definidas para la intensidad de uso {} ({})",
+                    intensity, conditions
+                );
+                return gains;
+            }
+        };
+        let hours = parts
+            .next()
+            .and_then(|h| h.trim_end_matches('h').parse::<usize>().ok())
+            .unwrap_or(12)
+            .min(24);
+        let start = if hours == 24 { 0 } else { 8 };
+        for g in gains.iter_mut().skip(start).take(hours) {
+            *g = tg.occupancy + tg.lighting + tg.equipment;
+        }
+    }
+    gains
+}
+
+impl Model {
+    /// Carga de refrigeración sensible de diseño de los espacios acondicionados (W)
+    ///
+    /// Devuelve la carga horaria de cada espacio, la hora punta y sus componentes.
+    /// La carga del edificio considera los multiplicadores de los espacios.
+    /// No se consideran los elementos en contacto con el terreno ni con otros espacios.
+    pub fn design_cooling_load(&self, conditions: &CoolingDesignConditions) -> CoolingLoadDetail {
+        self.design_cooling_load_with(conditions, &self.u_values())
+    }
+
+    /// design_cooling_load con las transmitancias térmicas ya calculadas (ver Model::u_values)
+    pub fn design_cooling_load_with(
+        &self,
+        conditions: &CoolingDesignConditions,
+        u_values: &UValues,
+    ) -> CoolingLoadDetail {
+        let hve = self.H_ve_with(u_values);
+        let (theta_max, daily_range) = (conditions.theta_max, conditions.daily_range);
+        let theta_e = design_day_temperatures(theta_max, daily_range);
+
+        let mut detail = CoolingLoadDetail {
+            theta_max,
+            daily_range,
+            theta_int: THETA_INT_COOLING,
+            ..Default::default()
+        };
+        let mut building = [0.0_f32; 24];
+
+        for space in self
+            .spaces
+            .iter()
+            .filter(|s| s.inside_tenv && s.space_type == SpaceType::CONDITIONED)
+        {
+            let h_ve = hve.by_space.get(&space.id).map(|p| p.h_ve).unwrap_or(0.0);
+            let h_ve = if space.multiplier > 0.0 {
+                h_ve / space.multiplier
+            } else {
+                0.0
+            };
+            let hourly = self.space_cooling_load(space, conditions, u_values, &theta_e, h_ve);
+            let (peak_hour, peak) =
+                hourly
+                    .iter()
+                    .enumerate()
+                    .fold((0, hourly[0]), |(ih, p), (h, c)| {
+                        if c.total > p.total {
+                            (h, *c)
+                        } else {
+                            (ih, p)
+                        }
+                    });
+            debug!(
+                "{} carga de refrigeración punta {:.0} W a las {}h ({:?})",
+                space.name, peak.total, peak_hour, peak
+            );
+            for (b, c) in building.iter_mut().zip(hourly.iter()) {
+                *b += c.total * space.multiplier;
+            }
+            detail.spaces.push(SpaceCoolingLoad {
+                id: space.id.clone(),
+                name: space.name.clone(),
+                peak_hour: peak_hour as u32,
+                peak,
+                hourly,
+            });
+        }
+
+        let (peak_hour, peak) =
+            building
+                .iter()
+                .enumerate()
+                .fold(
+                    (0, building[0]),
+                    |(ih, p), (h, &c)| {
+                        if c > p {
+                            (h, c)
+                        } else {
+                            (ih, p)
+                        }
+                    },
+                );
+        detail.peak_hour = peak_hour as u32;
+        detail.peak = peak;
+        info!(
+            "Carga de refrigeración punta del edificio {:.0} W a las {}h (θ_max={:.1}ºC)",
+            peak, peak_hour, theta_max
+        );
+        detail
+    }
+
+    /// Componentes horarios de la carga de refrigeración de un espacio (W)
+    fn space_cooling_load(
+        &self,
+        space: &Space,
+        conditions: &CoolingDesignConditions,
+        u_values: &UValues,
+        theta_e: &[f32; 24],
+        h_ve: f32,
+    ) -> Vec<CoolingLoadComponents> {
+        let mut hourly = vec![CoolingLoadComponents::default(); 24];
+
+        let internal =
+            internal_gains_profile(space.conditions.as_deref(), &conditions.tertiary_gains);
+        for (h, c) in hourly.iter_mut().enumerate() {
+            c.internal = internal[h] * space.area;
+            c.ventilation = h_ve * (theta_e[h] - THETA_INT_COOLING);
+        }
+
+        for wall in u_values
+            .index
+            .walls_of_space(&space.id)
+            .filter(|w| w.bounds == BoundaryType::EXTERIOR)
+        {
+            let irradiance = design_day_irradiance(conditions.latitude, wall.tilt, wall.azimuth);
+            if let Some(walls) = self.wall_cooling_load(wall, u_values, theta_e, &irradiance) {
+                for (c, q) in hourly.iter_mut().zip(walls.iter()) {
+                    c.walls += q;
+                }
+            }
+
+            for win in u_values.index.windows_of_wall(&wall.id) {
+                let (wincons, u) = match (u_values.index.get_wincons(win), u_values.window(win)) {
+                    (Some(wincons), Some(u)) => (wincons, u),
+                    _ => continue,
+                };
+                let activation = wincons
+                    .shading
+                    .as_ref()
+                    .map(|s| s.activation)
+                    .unwrap_or(ACTIVATION_DEFAULT);
+                for (h, c) in hourly.iter_mut().enumerate() {
+                    let g = if irradiance[h] > activation {
+                        wincons.gglshwi
+                    } else {
+                        wincons.gglwi
+                    };
+                    c.solar += win.fshobst * g * (1.0 - wincons.ff) * win.area * irradiance[h];
+                    c.windows += u * win.area * (theta_e[h] - THETA_INT_COOLING);
+                }
+            }
+        }
+
+        for c in hourly.iter_mut() {
+            c.total = c.solar + c.windows + c.walls + c.internal + c.ventilation;
+        }
+        hourly
+    }
+
+    /// Carga horaria de un opaco exterior con la temperatura sol-aire (W)
+    ///
+    /// Q(h) = U·A·[(θ̄_sa - θ_int) + f·(θ_sa(h - Δt) - θ̄_sa)], con el factor de amortiguamiento (f)
+    /// y el desfase (Δt) de la composición
+    fn wall_cooling_load(
+        &self,
+        wall: &Wall,
+        u_values: &UValues,
+        theta_e: &[f32; 24],
+        irradiance: &[f32; 24],
+    ) -> Option<[f32; 24]> {
+        let u = u_values.wall(wall)?;
+        let cons = u_values.index.get_wallcons(wall)?;
+        let sky = if wall.tilt < 45.0 {
+            SKY_CORRECTION
+        } else {
+            0.0
+        };
+        let mut theta_sa = [0.0; 24];
+        for h in 0..24 {
+            theta_sa[h] = theta_e[h] + cons.absorptance * irradiance[h] * RSE - sky;
+        }
+        let mean = theta_sa.iter().sum::<f32>() / 24.0;
+        let (f, shift) = if cons.decrement_factor > 0.0 {
+            (cons.decrement_factor, cons.time_shift.round() as usize % 24)
+        } else {
+            (1.0, 0)
+        };
+        let mut q = [0.0; 24];
+        for (h, qh) in q.iter_mut().enumerate() {
+            let lagged = theta_sa[(h + 24 - shift) % 24];
+            *qh = u * wall.area * ((mean - THETA_INT_COOLING) + f * (lagged - mean));
+        }
+        Some(q)
+    }
+}
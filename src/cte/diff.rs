@@ -0,0 +1,347 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Comparación de dos versiones de un modelo
+//!
+//! Los elementos (espacios, opacos, huecos, puentes térmicos y construcciones) se emparejan por
+//! nombre o, si no se encuentra, por id. Las referencias a otros elementos (espacio, construcción,
+//! opaco, espacio adyacente) se comparan por nombre, ya que los id pueden cambiar entre versiones.
+
+use std::collections::HashMap;
+
+use log::info;
+
+use super::{ElementDiff, FieldDiff, Model, ModelDiff, UValues, ValueDelta};
+
+/// Tolerancia para considerar que dos valores numéricos son distintos
+const TOLERANCE: f32 = 0.001;
+
+/// Valor de una propiedad de un elemento
+enum Field {
+    Num(f32),
+    OptNum(Option<f32>),
+    Text(String),
+}
+
+impl Field {
+    fn to_text(&self) -> String {
+        match self {
+            Field::Num(v) | Field::OptNum(Some(v)) => format!("{:.3}", v),
+            Field::OptNum(None) => "-".to_string(),
+            Field::Text(s) => s.clone(),
+        }
+    }
+
+    fn differs(&self, other: &Field) -> bool {
+        match (self, other) {
+            (Field::Num(a), Field::Num(b)) | (Field::OptNum(Some(a)), Field::OptNum(Some(b))) => {
+                (a - b).abs() > TOLERANCE
+            }
+            (Field::OptNum(None), Field::OptNum(None)) => false,
+            _ => self.to_text() != other.to_text(),
+        }
+    }
+}
+
+/// Lista de propiedades de un elemento
+type Fields = Vec<(&'static str, Field)>;
+
+impl Model {
+    /// Compara el modelo con otra versión y devuelve los elementos añadidos, eliminados y modificados
+    /// y la variación de los indicadores
+    ///
+    /// self es la versión inicial y other la versión final
+    pub fn diff(&self, other: &Model) -> ModelDiff {
+        let mut diff = ModelDiff::default();
+        let (u0, u1) = (self.u_values(), other.u_values());
+        let (names0, names1) = (NameIndex::new(self), NameIndex::new(other));
+
+        diff_elements(
+            &mut diff,
+            "Space",
+            &self.spaces,
+            &other.spaces,
+            |s| (&s.id, &s.name),
+            space_fields,
+            space_fields,
+        );
+        diff_elements(
+            &mut diff,
+            "Wall",
+            &self.walls,
+            &other.walls,
+            |w| (&w.id, &w.name),
+            |w| wall_fields(w, &u0, &names0),
+            |w| wall_fields(w, &u1, &names1),
+        );
+        diff_elements(
+            &mut diff,
+            "Window",
+            &self.windows,
+            &other.windows,
+            |w| (&w.id, &w.name),
+            |w| window_fields(w, &u0, &names0),
+            |w| window_fields(w, &u1, &names1),
+        );
+        diff_elements(
+            &mut diff,
+            "ThermalBridge",
+            &self.thermal_bridges,
+            &other.thermal_bridges,
+            |tb| (&tb.id, &tb.name),
+            tb_fields,
+            tb_fields,
+        );
+        diff_elements(
+            &mut diff,
+            "WallCons",
+            &self.wallcons,
+            &other.wallcons,
+            |c| (&c.id, &c.name),
+            wallcons_fields,
+            wallcons_fields,
+        );
+        diff_elements(
+            &mut diff,
+            "WindowCons",
+            &self.wincons,
+            &other.wincons,
+            |c| (&c.id, &c.name),
+            wincons_fields,
+            wincons_fields,
+        );
+
+        // Indicadores y desglose de la K
+        let (k0, k1) = (self.K_he2019_with(&u0), other.K_he2019_with(&u1));
+        diff.indicators = vec![
+            ValueDelta::new("K", k0.K, k1.K),
+            ValueDelta::new("walls_a", k0.walls_a, k1.walls_a),
+            ValueDelta::new("walls_a_u", k0.walls_a_u, k1.walls_a_u),
+            ValueDelta::new("windows_a", k0.windows_a, k1.windows_a),
+            ValueDelta::new("windows_a_u", k0.windows_a_u, k1.windows_a_u),
+            ValueDelta::new(
+                "thermal_bridges_l",
+                k0.thermal_bridges_l,
+                k1.thermal_bridges_l,
+            ),
+            ValueDelta::new(
+                "thermal_bridges_psi_l",
+                k0.thermal_bridges_psi_l,
+                k1.thermal_bridges_psi_l,
+            ),
+            ValueDelta::new(
+                "q_soljul",
+                self.q_soljul_with(&u0),
+                other.q_soljul_with(&u1),
+            ),
+            ValueDelta::new("n50", self.n50(), other.n50()),
+            ValueDelta::new("compacity", self.compacity(), other.compacity()),
+            ValueDelta::new("vol_env_net", self.vol_env_net(), other.vol_env_net()),
+            ValueDelta::new("a_ref", self.a_ref(), other.a_ref()),
+        ];
+
+        info!(
+            "Comparación de modelos: {} elementos añadidos, {} eliminados y {} modificados. K {:.2} -> {:.2} W/m²K",
+            diff.added.len(),
+            diff.removed.len(),
+            diff.changed.len(),
+            k0.K,
+            k1.K
+        );
+        diff
+    }
+}
+
+/// Nombres de los elementos referenciados por otros (espacios, opacos y construcciones), por id
+struct NameIndex<'a> {
+    spaces: HashMap<&'a str, &'a str>,
+    walls: HashMap<&'a str, &'a str>,
+    wallcons: HashMap<&'a str, &'a str>,
+    wincons: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> NameIndex<'a> {
+    fn new(model: &'a Model) -> Self {
+        Self {
+            spaces: model
+                .spaces
+                .iter()
+                .map(|e| (e.id.as_str(), e.name.as_str()))
+                .collect(),
+            walls: model
+                .walls
+                .iter()
+                .map(|e| (e.id.as_str(), e.name.as_str()))
+                .collect(),
+            wallcons: model
+                .wallcons
+                .iter()
+                .map(|e| (e.id.as_str(), e.name.as_str()))
+                .collect(),
+            wincons: model
+                .wincons
+                .iter()
+                .map(|e| (e.id.as_str(), e.name.as_str()))
+                .collect(),
+        }
+    }
+
+    /// Nombre del elemento con el id dado o, si no se encuentra, el propio id
+    fn name(map: &HashMap<&'a str, &'a str>, id: &str) -> String {
+        map.get(id).copied().unwrap_or(id).to_string()
+    }
+}
+
+fn space_fields(s: &super::Space) -> Fields {
+    vec![
+        ("area", Field::Num(s.area)),
+        ("multiplier", Field::Num(s.multiplier)),
+        ("type", Field::Text(format!("{:?}", s.space_type))),
+        ("inside_tenv", Field::Text(s.inside_tenv.to_string())),
+        ("height", Field::Num(s.height)),
+        ("n_v", Field::OptNum(s.n_v)),
+        ("z", Field::Num(s.z)),
+    ]
+}
+
+fn wall_fields(w: &super::Wall, u_values: &UValues, names: &NameIndex) -> Fields {
+    vec![
+        ("A", Field::Num(w.area)),
+        ("bounds", Field::Text(format!("{:?}", w.bounds))),
+        (
+            "cons",
+            Field::Text(NameIndex::name(&names.wallcons, &w.cons)),
+        ),
+        (
+            "space",
+            Field::Text(NameIndex::name(&names.spaces, &w.space)),
+        ),
+        (
+            "nextto",
+            Field::Text(
+                w.nextto
+                    .as_ref()
+                    .map(|id| NameIndex::name(&names.spaces, id))
+                    .unwrap_or_default(),
+            ),
+        ),
+        ("azimuth", Field::Num(w.azimuth)),
+        ("tilt", Field::Num(w.tilt)),
+        ("U", Field::OptNum(u_values.wall(w))),
+    ]
+}
+
+fn window_fields(w: &super::Window, u_values: &UValues, names: &NameIndex) -> Fields {
+    vec![
+        ("A", Field::Num(w.area)),
+        (
+            "cons",
+            Field::Text(NameIndex::name(&names.wincons, &w.cons)),
+        ),
+        ("wall", Field::Text(NameIndex::name(&names.walls, &w.wall))),
+        ("fshobst", Field::Num(w.fshobst)),
+        ("U", Field::OptNum(u_values.window(w))),
+    ]
+}
+
+fn tb_fields(tb: &super::ThermalBridge) -> Fields {
+    vec![
+        ("L", Field::Num(tb.l)),
+        ("psi", Field::Num(tb.psi)),
+        ("frsi", Field::OptNum(tb.frsi)),
+    ]
+}
+
+fn wallcons_fields(c: &super::WallCons) -> Fields {
+    vec![
+        ("thickness", Field::Num(c.thickness)),
+        ("R_intrinsic", Field::Num(c.r_intrinsic)),
+        ("absorptance", Field::Num(c.absorptance)),
+        ("kappa_1", Field::Num(c.kappa_1)),
+        ("kappa_2", Field::Num(c.kappa_2)),
+    ]
+}
+
+fn wincons_fields(c: &super::WindowCons) -> Fields {
+    vec![
+        ("U", Field::Num(c.u)),
+        ("Ff", Field::Num(c.ff)),
+        ("gglwi", Field::Num(c.gglwi)),
+        ("gglshwi", Field::Num(c.gglshwi)),
+        ("C_100", Field::Num(c.infcoeff_100)),
+    ]
+}
+
+/// Compara dos listas de elementos de un tipo y añade las diferencias al reporte
+///
+/// Los elementos se emparejan por nombre y, si no se encuentra, por id, usando índices
+/// de las posiciones de los elementos de la versión final
+fn diff_elements<'a, T: 'a>(
+    diff: &mut ModelDiff,
+    kind: &str,
+    before: &'a [T],
+    after: &'a [T],
+    key: impl Fn(&'a T) -> (&'a String, &'a String),
+    fields_before: impl Fn(&'a T) -> Fields,
+    fields_after: impl Fn(&'a T) -> Fields,
+) {
+    let mut by_name: HashMap<&String, Vec<usize>> = HashMap::new();
+    let mut by_id: HashMap<&String, Vec<usize>> = HashMap::new();
+    for (i, e) in after.iter().enumerate() {
+        let (id, name) = key(e);
+        by_name.entry(name).or_default().push(i);
+        by_id.entry(id).or_default().push(i);
+    }
+    let mut matched = vec![false; after.len()];
+    let unmatched = |index: &HashMap<&String, Vec<usize>>, k: &String, matched: &[bool]| {
+        index
+            .get(k)
+            .and_then(|v| v.iter().copied().find(|&i| !matched[i]))
+    };
+    for elem in before {
+        let (id, name) = key(elem);
+        let pos = unmatched(&by_name, name, &matched).or_else(|| unmatched(&by_id, id, &matched));
+        let other = match pos {
+            Some(i) => {
+                matched[i] = true;
+                &after[i]
+            }
+            None => {
+                diff.removed.push(ElementDiff::new(kind, id, name));
+                continue;
+            }
+        };
+        let (other_id, other_name) = key(other);
+        let mut changes = fields_before(elem)
+            .iter()
+            .zip(fields_after(other).iter())
+            .filter(|((_, f0), (_, f1))| f0.differs(f1))
+            .map(|((field, f0), (_, f1))| FieldDiff {
+                field: field.to_string(),
+                before: f0.to_text(),
+                after: f1.to_text(),
+            })
+            .collect::<Vec<_>>();
+        if name != other_name {
+            changes.insert(
+                0,
+                FieldDiff {
+                    field: "name".to_string(),
+                    before: name.clone(),
+                    after: other_name.clone(),
+                },
+            );
+        }
+        if !changes.is_empty() {
+            diff.changed.push(ElementDiff {
+                changes,
+                ..ElementDiff::new(kind, other_id, other_name)
+            });
+        }
+    }
+    for (elem, _) in after.iter().zip(matched).filter(|(_, m)| !m) {
+        let (id, name) = key(elem);
+        diff.added.push(ElementDiff::new(kind, id, name));
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Características térmicas dinámicas de las composiciones de capas
+//! - UNE-EN ISO 13786:2017 Prestaciones térmicas de los productos y componentes para edificación.
+//!   Características térmicas dinámicas. Métodos de cálculo.
+//!
+//! Se usa el convenio de la norma, con el lado 1 correspondiente a la cara interior del elemento.
+//! Las resistencias superficiales son las de un elemento vertical (flujo horizontal).
+
+use std::{
+    collections::HashMap,
+    f64::consts::PI,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use anyhow::{format_err, Error};
+
+use super::model_impl::{RSE, RSI_HORIZONTAL};
+use crate::bdl;
+
+/// Periodo de las variaciones de temperatura (s), ciclo diario
+pub const PERIOD: f64 = 86400.0;
+
+/// Características térmicas dinámicas de una composición de capas
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DynamicProperties {
+    /// Capacidad térmica interior por unidad de área, κ1 (J/m2K)
+    pub kappa_1: f32,
+    /// Capacidad térmica exterior por unidad de área, κ2 (J/m2K)
+    pub kappa_2: f32,
+    /// Transmitancia térmica periódica, |Y12| (W/m2K)
+    pub y_12: f32,
+    /// Factor de amortiguamiento, f = |Y12| / U (-)
+    pub decrement_factor: f32,
+    /// Desfase de la transmitancia térmica periódica, Δt_f (h)
+    pub time_shift: f32,
+}
+
+/// Número complejo mínimo para las operaciones con matrices de transferencia
+#[derive(Debug, Copy, Clone)]
+struct Complex(f64, f64);
+
+impl Complex {
+    fn abs(self) -> f64 {
+        self.0.hypot(self.1)
+    }
+    fn arg(self) -> f64 {
+        self.1.atan2(self.0)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, o: Self) -> Self {
+        Complex(self.0 + o.0, self.1 + o.1)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, o: Self) -> Self {
+        Complex(self.0 - o.0, self.1 - o.1)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, o: Self) -> Self {
+        Complex(self.0 * o.0 - self.1 * o.1, self.0 * o.1 + self.1 * o.0)
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+    fn div(self, o: Self) -> Self {
+        let den = o.0 * o.0 + o.1 * o.1;
+        Complex(
+            (self.0 * o.0 + self.1 * o.1) / den,
+            (self.1 * o.0 - self.0 * o.1) / den,
+        )
+    }
+}
+
+/// Matriz de transferencia térmica [[Z11, Z12], [Z21, Z22]]
+type Matrix = [[Complex; 2]; 2];
+
+fn matmul(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[Complex(0.0, 0.0); 2]; 2];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, val) in row.iter_mut().enumerate() {
+            *val = a[i][0] * b[0][j] + a[i][1] * b[1][j];
+        }
+    }
+    m
+}
+
+/// Matriz de transferencia de una capa sin masa térmica o de una resistencia superficial
+/// UNE-EN ISO 13786:2017, ecuación (6)
+fn resistance_matrix(r: f64) -> Matrix {
+    [
+        [Complex(1.0, 0.0), Complex(-r, 0.0)],
+        [Complex(0.0, 0.0), Complex(1.0, 0.0)],
+    ]
+}
+
+/// Matriz de transferencia de una capa homogénea
+/// UNE-EN ISO 13786:2017, ecuaciones (3) a (5)
+fn layer_matrix(thickness: f64, conductivity: f64, density: f64, specificheat: f64) -> Matrix {
+    // Profundidad de penetración periódica, δ
+    let delta = (conductivity * PERIOD / (PI * density * specificheat)).sqrt();
+    let xi = thickness / delta;
+    let (sh, ch) = (xi.sinh(), xi.cosh());
+    let (s, c) = (xi.sin(), xi.cos());
+    let z11 = Complex(ch * c, sh * s);
+    let z12 = Complex(
+        -delta / (2.0 * conductivity) * (sh * c + ch * s),
+        -delta / (2.0 * conductivity) * (ch * s - sh * c),
+    );
+    let z21 = Complex(
+        -conductivity / delta * (sh * c - ch * s),
+        -conductivity / delta * (sh * c + ch * s),
+    );
+    [[z11, z12], [z21, z11]]
+}
+
+/// Características térmicas dinámicas de una composición de capas
+///
+/// Las capas de la composición se ordenan de exterior a interior.
+/// Los materiales definidos por resistencia o sin capacidad térmica se tratan como capas sin masa.
+pub fn dynamic_properties(
+    cons: &bdl::WallCons,
+    materialsdb: &HashMap<String, bdl::Material>,
+) -> Result<DynamicProperties, Error> {
+    let rsi = f64::from(RSI_HORIZONTAL);
+    let rse = f64::from(RSE);
+
+    // Z = Z_se · Z_N ··· Z_1 · Z_si, con la capa 1 en la cara interior
+    let mut z = resistance_matrix(rsi);
+    let mut r_total = rsi + rse;
+    for (name, thk) in cons.material.iter().zip(&cons.thickness).rev() {
+        let mat = materialsdb.get(name).ok_or_else(|| {
+            format_err!(
+                "No se encuentra el material \"{}\" de la composición de capas \"{}\"",
+                name,
+                cons.name
+            )
+        })?;
+        let thk = f64::from(*thk);
+        let layer = match (mat.properties, mat.resistance) {
+            (Some(props), _) if props.conductivity != 0.0 => {
+                let lambda = f64::from(props.conductivity);
+                let rho_c = f64::from(props.density) * f64::from(props.specificheat);
+                r_total += thk / lambda;
+                if rho_c > 0.0 {
+                    layer_matrix(
+                        thk,
+                        lambda,
+                        f64::from(props.density),
+                        f64::from(props.specificheat),
+                    )
+                } else {
+                    resistance_matrix(thk / lambda)
+                }
+            }
+            (None, Some(r)) => {
+                r_total += f64::from(r);
+                resistance_matrix(f64::from(r))
+            }
+            _ => {
+                return Err(format_err!(
+                    "Material \"{}\" no definido por conductividad o resistencia",
+                    name
+                ))
+            }
+        };
+        z = matmul(&layer, &z);
+    }
+    z = matmul(&resistance_matrix(rse), &z);
+
+    let [[z11, z12], [_, z22]] = z;
+    let t_2pi = PERIOD / (2.0 * PI);
+    // UNE-EN ISO 13786:2017, ecuaciones (11), (12) y (17)
+    let one = Complex(1.0, 0.0);
+    let kappa_1 = t_2pi * ((z11 - one) / z12).abs();
+    let kappa_2 = t_2pi * ((z22 - one) / z12).abs();
+    let y_12 = Complex(-1.0, 0.0) / z12;
+    // Desfase positivo cuando la respuesta se retrasa respecto a la solicitación
+    let mut time_shift = -y_12.arg() * t_2pi / 3600.0;
+    if time_shift < 0.0 {
+        time_shift += PERIOD / 3600.0;
+    }
+    let u = 1.0 / r_total;
+
+    Ok(DynamicProperties {
+        kappa_1: kappa_1 as f32,
+        kappa_2: kappa_2 as f32,
+        y_12: y_12.abs() as f32,
+        decrement_factor: (y_12.abs() / u) as f32,
+        time_shift: time_shift as f32,
+    })
+}
@@ -0,0 +1,498 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Modificación del modelo manteniendo la integridad de las referencias entre elementos
+//!
+//! Los opacos hacen referencia a su espacio, a su espacio adyacente y a su construcción, y los
+//! huecos a su opaco y a su construcción. Al añadir elementos se comprueban sus referencias, al
+//! eliminarlos se rechaza la operación o se eliminan también los elementos dependientes y al
+//! renombrarlos o fusionar construcciones se actualizan las referencias, de modo que el modelo
+//! sigue siendo coherente (ver check_model) tras cada operación.
+//!
+//! Los elementos con id basado en su nombre (ver ids.rs) cambian de id al renombrarlos. Los datos
+//! de comparación con HULC (extra) se identifican por el nombre del opaco y se actualizan también.
+
+use anyhow::{bail, Error};
+use log::{debug, info};
+
+use super::{
+    BoundaryType, ElementKind, Model, RemovePolicy, Space, ThermalBridge, Wall, WallCons, Window,
+    WindowCons,
+};
+
+impl Model {
+    /// Añade un espacio y devuelve su id
+    ///
+    /// Si el espacio no tiene id se le asigna uno basado en su nombre
+    pub fn add_space(&mut self, mut space: Space) -> Result<String, Error> {
+        self.check_new_element(ElementKind::Space, &mut space.id, &space.name)?;
+        let id = space.id.clone();
+        self.spaces.push(space);
+        Ok(id)
+    }
+
+    /// Añade un opaco y devuelve su id
+    ///
+    /// El espacio, el espacio adyacente y la construcción del opaco deben existir en el modelo
+    pub fn add_wall(&mut self, mut wall: Wall) -> Result<String, Error> {
+        self.check_new_element(ElementKind::Wall, &mut wall.id, &wall.name)?;
+        self.check_ref(&wall.name, ElementKind::Space, &wall.space)?;
+        if let Some(nextto) = wall.nextto.as_deref() {
+            self.check_ref(&wall.name, ElementKind::Space, nextto)?;
+        }
+        self.check_ref(&wall.name, ElementKind::WallCons, &wall.cons)?;
+        let id = wall.id.clone();
+        self.walls.push(wall);
+        Ok(id)
+    }
+
+    /// Añade un hueco y devuelve su id
+    ///
+    /// El opaco y la construcción del hueco deben existir en el modelo
+    pub fn add_window(&mut self, mut window: Window) -> Result<String, Error> {
+        self.check_new_element(ElementKind::Window, &mut window.id, &window.name)?;
+        self.check_ref(&window.name, ElementKind::Wall, &window.wall)?;
+        self.check_ref(&window.name, ElementKind::WindowCons, &window.cons)?;
+        let id = window.id.clone();
+        self.windows.push(window);
+        Ok(id)
+    }
+
+    /// Añade un puente térmico y devuelve su id
+    pub fn add_thermal_bridge(&mut self, mut tb: ThermalBridge) -> Result<String, Error> {
+        self.check_new_element(ElementKind::ThermalBridge, &mut tb.id, &tb.name)?;
+        let id = tb.id.clone();
+        self.thermal_bridges.push(tb);
+        Ok(id)
+    }
+
+    /// Añade una construcción de opaco y devuelve su id
+    pub fn add_wallcons(&mut self, mut cons: WallCons) -> Result<String, Error> {
+        self.check_new_element(ElementKind::WallCons, &mut cons.id, &cons.name)?;
+        let id = cons.id.clone();
+        self.wallcons.push(cons);
+        Ok(id)
+    }
+
+    /// Añade una construcción de hueco y devuelve su id
+    pub fn add_wincons(&mut self, mut cons: WindowCons) -> Result<String, Error> {
+        self.check_new_element(ElementKind::WindowCons, &mut cons.id, &cons.name)?;
+        let id = cons.id.clone();
+        self.wincons.push(cons);
+        Ok(id)
+    }
+
+    /// Elimina un elemento y devuelve la lista de elementos eliminados (tipo e id)
+    ///
+    /// Dependen de un espacio sus opacos, de un opaco sus huecos y de una construcción los elementos
+    /// que la usan. Con RemovePolicy::Reject la operación falla si hay elementos dependientes o
+    /// opacos de otros espacios adyacentes al espacio y con RemovePolicy::Cascade se eliminan los
+    /// elementos dependientes, junto con los que dependen de ellos, y los opacos adyacentes pasan a
+    /// estar en contacto con el aire exterior (sin espacio adyacente).
+    pub fn remove(
+        &mut self,
+        kind: ElementKind,
+        id: &str,
+        policy: RemovePolicy,
+    ) -> Result<Vec<(ElementKind, String)>, Error> {
+        if self.element_name(kind, id).is_none() {
+            bail!("No existe el elemento {} con id {}", kind, id);
+        }
+
+        let mut referencing = self.dependants(kind, id);
+        if kind == ElementKind::Space {
+            referencing.extend(
+                self.walls
+                    .iter()
+                    .filter(|w| w.space != id && w.nextto.as_deref() == Some(id))
+                    .map(|w| (ElementKind::Wall, w.id.clone())),
+            );
+        }
+        if policy == RemovePolicy::Reject && !referencing.is_empty() {
+            let names = referencing
+                .iter()
+                .map(|(k, i)| {
+                    let name = self.element_name(*k, i).unwrap_or(i.as_str());
+                    format!("{} {}", k, name)
+                })
+                .collect::<Vec<_>>();
+            bail!(
+                "No se puede eliminar el elemento {} con id {} porque otros elementos hacen referencia a él: {}",
+                kind,
+                id,
+                names.join(", ")
+            );
+        }
+
+        let mut removed = vec![(kind, id.to_string())];
+        let mut i = 0;
+        while i < removed.len() {
+            let (k, elemid) = removed[i].clone();
+            for dep in self.dependants(k, &elemid) {
+                if !removed.contains(&dep) {
+                    removed.push(dep);
+                }
+            }
+            i += 1;
+        }
+
+        let is_removed = |k: ElementKind, elemid: &str| {
+            removed
+                .iter()
+                .any(|(rk, rid)| *rk == k && rid.as_str() == elemid)
+        };
+        let removed_wall_names = self
+            .walls
+            .iter()
+            .filter(|w| is_removed(ElementKind::Wall, &w.id))
+            .map(|w| w.name.clone())
+            .collect::<Vec<_>>();
+        self.spaces
+            .retain(|e| !is_removed(ElementKind::Space, &e.id));
+        self.walls.retain(|e| !is_removed(ElementKind::Wall, &e.id));
+        self.windows
+            .retain(|e| !is_removed(ElementKind::Window, &e.id));
+        self.thermal_bridges
+            .retain(|e| !is_removed(ElementKind::ThermalBridge, &e.id));
+        self.wallcons
+            .retain(|e| !is_removed(ElementKind::WallCons, &e.id));
+        self.wincons
+            .retain(|e| !is_removed(ElementKind::WindowCons, &e.id));
+        // Los opacos adyacentes a espacios eliminados pasan a estar en contacto con el exterior
+        let is_removed_space = |spaceid: Option<&str>| {
+            spaceid
+                .map(|s| is_removed(ElementKind::Space, s))
+                .unwrap_or(false)
+        };
+        for w in self.walls.iter_mut() {
+            if is_removed_space(w.nextto.as_deref()) {
+                debug!(
+                    "Opaco {} ({}) sin espacio adyacente, en contacto con el exterior",
+                    w.id, w.name
+                );
+                w.nextto = None;
+                w.bounds = BoundaryType::EXTERIOR;
+            }
+        }
+        if let Some(extra) = self.extra.as_mut() {
+            extra.retain(|e| !removed_wall_names.contains(&e.name));
+            for e in extra.iter_mut() {
+                if is_removed_space(e.nextspace.as_deref()) {
+                    e.nextspace = None;
+                    e.nextspacetype = None;
+                    e.bounds = BoundaryType::EXTERIOR;
+                }
+            }
+        }
+
+        info!(
+            "Eliminado el elemento {} con id {} y {} elementos dependientes",
+            kind,
+            id,
+            removed.len() - 1
+        );
+        Ok(removed)
+    }
+
+    /// Cambia el nombre de un elemento
+    ///
+    /// Si el elemento tiene un id basado en su nombre se le asigna el id correspondiente al nuevo
+    /// nombre y se actualizan las referencias a él
+    pub fn rename(&mut self, kind: ElementKind, id: &str, name: &str) -> Result<(), Error> {
+        let old_name = match self.element_name(kind, id) {
+            Some(old_name) => old_name.to_string(),
+            None => bail!("No existe el elemento {} con id {}", kind, id),
+        };
+        if name.trim().is_empty() {
+            bail!("Nombre vacío para el elemento {} con id {}", kind, id);
+        }
+        if self
+            .element_keys(kind)
+            .iter()
+            .any(|(eid, ename)| *eid != id && *ename == name)
+        {
+            bail!("Ya existe un elemento {} con nombre {}", kind, name);
+        }
+
+        let new_id = if id == self.named_id(&kind.to_string(), &old_name) {
+            self.named_id(&kind.to_string(), name)
+        } else {
+            id.to_string()
+        };
+        match kind {
+            ElementKind::Space => {
+                if let Some(e) = self.spaces.iter_mut().find(|e| e.id == id) {
+                    e.id = new_id.clone();
+                    e.name = name.to_string();
+                }
+            }
+            ElementKind::Wall => {
+                if let Some(e) = self.walls.iter_mut().find(|e| e.id == id) {
+                    e.id = new_id.clone();
+                    e.name = name.to_string();
+                }
+                if let Some(extra) = self.extra.as_mut() {
+                    extra
+                        .iter_mut()
+                        .filter(|e| e.name == old_name)
+                        .for_each(|e| e.name = name.to_string());
+                }
+            }
+            ElementKind::Window => {
+                if let Some(e) = self.windows.iter_mut().find(|e| e.id == id) {
+                    e.id = new_id.clone();
+                    e.name = name.to_string();
+                }
+            }
+            ElementKind::ThermalBridge => {
+                if let Some(e) = self.thermal_bridges.iter_mut().find(|e| e.id == id) {
+                    e.id = new_id.clone();
+                    e.name = name.to_string();
+                }
+            }
+            ElementKind::WallCons => {
+                if let Some(e) = self.wallcons.iter_mut().find(|e| e.id == id) {
+                    e.id = new_id.clone();
+                    e.name = name.to_string();
+                }
+            }
+            ElementKind::WindowCons => {
+                if let Some(e) = self.wincons.iter_mut().find(|e| e.id == id) {
+                    e.id = new_id.clone();
+                    e.name = name.to_string();
+                }
+            }
+        }
+        if new_id != id {
+            self.replace_refs(kind, id, &new_id);
+        }
+        debug!(
+            "Elemento {} {} ({}) renombrado como {} ({})",
+            kind, old_name, id, name, new_id
+        );
+        Ok(())
+    }
+
+    /// Fusiona las construcciones de opacos y de huecos con las mismas propiedades (salvo id,
+    /// nombre y grupo) y devuelve la lista de pares (id eliminado, id conservado)
+    ///
+    /// Se conserva la primera construcción de cada grupo de duplicadas y se redirigen a ella las
+    /// referencias de los elementos que usaban las demás
+    pub fn merge_duplicate_constructions(&mut self) -> Vec<(String, String)> {
+        let wallcons_merged = duplicates(&self.wallcons, |c| &c.id, same_wallcons);
+        let wincons_merged = duplicates(&self.wincons, |c| &c.id, same_wincons);
+
+        for (old, new) in &wallcons_merged {
+            self.replace_refs(ElementKind::WallCons, old, new);
+        }
+        for (old, new) in &wincons_merged {
+            self.replace_refs(ElementKind::WindowCons, old, new);
+        }
+        self.wallcons
+            .retain(|c| !wallcons_merged.iter().any(|(old, _)| *old == c.id));
+        self.wincons
+            .retain(|c| !wincons_merged.iter().any(|(old, _)| *old == c.id));
+
+        info!(
+            "Fusionadas {} construcciones de opacos y {} construcciones de huecos duplicadas",
+            wallcons_merged.len(),
+            wincons_merged.len()
+        );
+        wallcons_merged.into_iter().chain(wincons_merged).collect()
+    }
+
+    /// Lista de pares (id, nombre) de los elementos de un tipo
+    fn element_keys(&self, kind: ElementKind) -> Vec<(&str, &str)> {
+        match kind {
+            ElementKind::Space => self
+                .spaces
+                .iter()
+                .map(|e| (e.id.as_str(), e.name.as_str()))
+                .collect(),
+            ElementKind::Wall => self
+                .walls
+                .iter()
+                .map(|e| (e.id.as_str(), e.name.as_str()))
+                .collect(),
+            ElementKind::Window => self
+                .windows
+                .iter()
+                .map(|e| (e.id.as_str(), e.name.as_str()))
+                .collect(),
+            ElementKind::ThermalBridge => self
+                .thermal_bridges
+                .iter()
+                .map(|e| (e.id.as_str(), e.name.as_str()))
+                .collect(),
+            ElementKind::WallCons => self
+                .wallcons
+                .iter()
+                .map(|e| (e.id.as_str(), e.name.as_str()))
+                .collect(),
+            ElementKind::WindowCons => self
+                .wincons
+                .iter()
+                .map(|e| (e.id.as_str(), e.name.as_str()))
+                .collect(),
+        }
+    }
+
+    /// Nombre del elemento de un tipo con el id dado
+    fn element_name(&self, kind: ElementKind, id: &str) -> Option<&str> {
+        self.element_keys(kind)
+            .into_iter()
+            .find(|(eid, _)| *eid == id)
+            .map(|(_, name)| name)
+    }
+
+    /// Comprueba que el nombre y el id de un nuevo elemento no están en uso
+    ///
+    /// Si el id está vacío se asigna un id basado en el nombre
+    fn check_new_element(
+        &self,
+        kind: ElementKind,
+        id: &mut String,
+        name: &str,
+    ) -> Result<(), Error> {
+        if name.trim().is_empty() {
+            bail!("Nombre vacío para el nuevo elemento {}", kind);
+        }
+        if id.is_empty() {
+            *id = self.named_id(&kind.to_string(), name);
+        }
+        for (eid, ename) in self.element_keys(kind) {
+            if eid == id {
+                bail!("Ya existe un elemento {} con id {}", kind, id);
+            }
+            if ename == name {
+                bail!("Ya existe un elemento {} con nombre {}", kind, name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Comprueba que existe el elemento al que hace referencia un nuevo elemento
+    fn check_ref(&self, name: &str, kind: ElementKind, id: &str) -> Result<(), Error> {
+        if self.element_name(kind, id).is_none() {
+            bail!(
+                "El elemento {} hace referencia a un elemento {} inexistente con id {}",
+                name,
+                kind,
+                id
+            );
+        }
+        Ok(())
+    }
+
+    /// Elementos que hacen referencia directamente a un elemento (tipo e id)
+    fn dependants(&self, kind: ElementKind, id: &str) -> Vec<(ElementKind, String)> {
+        match kind {
+            ElementKind::Space => self
+                .walls
+                .iter()
+                .filter(|w| w.space == id)
+                .map(|w| (ElementKind::Wall, w.id.clone()))
+                .collect(),
+            ElementKind::Wall => self
+                .windows
+                .iter()
+                .filter(|w| w.wall == id)
+                .map(|w| (ElementKind::Window, w.id.clone()))
+                .collect(),
+            ElementKind::WallCons => self
+                .walls
+                .iter()
+                .filter(|w| w.cons == id)
+                .map(|w| (ElementKind::Wall, w.id.clone()))
+                .collect(),
+            ElementKind::WindowCons => self
+                .windows
+                .iter()
+                .filter(|w| w.cons == id)
+                .map(|w| (ElementKind::Window, w.id.clone()))
+                .collect(),
+            ElementKind::Window | ElementKind::ThermalBridge => Vec::new(),
+        }
+    }
+
+    /// Sustituye las referencias a un elemento por referencias a otro elemento del mismo tipo
+    fn replace_refs(&mut self, kind: ElementKind, old: &str, new: &str) {
+        let replace = |r: &mut String| {
+            if r == old {
+                *r = new.to_string();
+            }
+        };
+        match kind {
+            ElementKind::Space => {
+                for w in self.walls.iter_mut() {
+                    replace(&mut w.space);
+                    if let Some(nextto) = w.nextto.as_mut() {
+                        replace(nextto);
+                    }
+                }
+                for e in self.extra.iter_mut().flatten() {
+                    if let Some(nextspace) = e.nextspace.as_mut() {
+                        replace(nextspace);
+                    }
+                }
+            }
+            ElementKind::Wall => self.windows.iter_mut().for_each(|w| replace(&mut w.wall)),
+            ElementKind::WallCons => {
+                self.walls.iter_mut().for_each(|w| replace(&mut w.cons));
+                self.extra
+                    .iter_mut()
+                    .flatten()
+                    .for_each(|e| replace(&mut e.cons));
+            }
+            ElementKind::WindowCons => self.windows.iter_mut().for_each(|w| replace(&mut w.cons)),
+            ElementKind::Window | ElementKind::ThermalBridge => (),
+        }
+    }
+}
+
+/// Pares (id del duplicado, id del original) de los elementos duplicados de una lista
+fn duplicates<T>(
+    elements: &[T],
+    id: impl Fn(&T) -> &String,
+    same: impl Fn(&T, &T) -> bool,
+) -> Vec<(String, String)> {
+    let mut originals: Vec<&T> = Vec::new();
+    let mut merged = Vec::new();
+    for e in elements {
+        match originals.iter().find(|&&o| same(o, e)) {
+            Some(o) => merged.push((id(e).clone(), id(o).clone())),
+            None => originals.push(e),
+        }
+    }
+    merged
+}
+
+fn same_wallcons(a: &WallCons, b: &WallCons) -> bool {
+    let props = |c: &WallCons| {
+        [
+            c.thickness,
+            c.r_intrinsic,
+            c.absorptance,
+            c.kappa_1,
+            c.kappa_2,
+            c.y_12,
+            c.decrement_factor,
+            c.time_shift,
+        ]
+    };
+    props(a) == props(b)
+}
+
+fn same_wincons(a: &WindowCons, b: &WindowCons) -> bool {
+    let props = |c: &WindowCons| [c.u, c.ff, c.gglwi, c.gglshwi, c.infcoeff_100];
+    let same_shading = match (&a.shading, &b.shading) {
+        (None, None) => true,
+        (Some(sa), Some(sb)) => {
+            sa.position == sb.position
+                && [sa.tau_e, sa.rho_e, sa.activation] == [sb.tau_e, sb.rho_e, sb.activation]
+        }
+        _ => false,
+    };
+    props(a) == props(b) && same_shading
+}
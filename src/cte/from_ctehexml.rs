@@ -4,7 +4,11 @@
 
 //! Conversión desde CtehexmlData a cte::Model
 
-use std::{collections::BTreeMap, convert::TryFrom, convert::TryInto};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    convert::TryInto,
+};
 
 use anyhow::{anyhow, format_err, Error};
 use log::warn;
@@ -15,9 +19,12 @@ use crate::{
     utils::{fround2, fround3, orientation_bdl_to_52016, uuid_from_obj},
 };
 
+use super::dynamic::{dynamic_properties, DynamicProperties};
+
 pub use super::{
-    BoundaryType, Meta, Model, Orientation, Space, SpaceType, ThermalBridge, Tilt, Wall, WallCons,
-    Window, WindowCons,
+    climatezone_from_hulc, climatezone_from_location, BoundaryType, ClimateZone, IdScheme, Meta,
+    Model, Orientation, Space, SpaceType, ThermalBridge, Tilt, Wall, WallCons, Window, WindowCons,
+    WindowUMethod,
 };
 
 // Conversiones de BDL a tipos CTE -------------------
@@ -36,13 +43,25 @@ impl From<bdl::BoundaryType> for BoundaryType {
 impl TryFrom<&ctehexml::CtehexmlData> for Model {
     type Error = Error;
     fn try_from(d: &ctehexml::CtehexmlData) -> Result<Self, Self::Error> {
+        Model::try_from_ctehexml(d, WindowUMethod::default(), IdScheme::default())
+    }
+}
+
+impl Model {
+    /// Genera el modelo a partir de los datos del .ctehexml usando el método indicado para el cálculo de la U de huecos
+    /// y el método de generación de id indicado
+    pub fn try_from_ctehexml(
+        d: &ctehexml::CtehexmlData,
+        u_method: WindowUMethod,
+        ids: IdScheme,
+    ) -> Result<Self, Error> {
         let bdl = &d.bdldata;
 
         let mut walls = walls_from_bdl(&bdl)?;
         let mut windows = windows_from_bdl(&walls, &bdl);
         let thermal_bridges = thermal_bridges_from_bdl(&bdl);
         let wallcons = wallcons_from_bdl(&walls, &bdl)?;
-        let wincons = windowcons_from_bdl(&bdl)?;
+        let wincons = windowcons_from_bdl(&bdl, u_method)?;
         let spaces = spaces_from_bdl(&bdl)?;
 
         // Cambia referencias a nombres por id's
@@ -96,11 +115,7 @@ impl TryFrom<&ctehexml::CtehexmlData> for Model {
             is_new_building: dg.tipo_definicion.as_str() == "Nuevo",
             is_dwelling,
             num_dwellings: dg.num_viviendas_bloque,
-            climate: dg
-                .archivo_climatico
-                .as_str()
-                .try_into()
-                .map_err(|e| anyhow!("ERROR: {}", e))?,
+            climate: climatezone_from_datos_generales(dg)?,
             global_ventilation_l_s: if is_dwelling {
                 Some(dg.valor_impulsion_aire)
             } else {
@@ -109,9 +124,11 @@ impl TryFrom<&ctehexml::CtehexmlData> for Model {
             n50_test_ach: dg.valor_n50_medido,
             d_perim_insulation,
             rn_perim_insulation,
+            hygrometry: dg.clase_higrometria,
+            id_namespace: None,
         };
 
-        Ok(Model {
+        let mut model = Model {
             meta,
             walls,
             windows,
@@ -120,10 +137,56 @@ impl TryFrom<&ctehexml::CtehexmlData> for Model {
             wincons,
             wallcons,
             extra: None,
-        })
+        };
+        if ids == IdScheme::Named {
+            model.set_named_ids();
+        }
+        Ok(model)
     }
 }
 
+/// Zona climática del proyecto
+///
+/// Se obtiene del nombre del archivo climático y se contrasta con la zona indicada en HULC para
+/// la localidad y con la que corresponde a su provincia y altitud (DB-HE Anejo B).
+/// Si el archivo climático no corresponde a una zona climática (p.e. archivos climáticos propios)
+/// se usa la zona indicada en HULC o, en su defecto, la obtenida con la provincia y altitud.
+fn climatezone_from_datos_generales(dg: &ctehexml::DatosGenerales) -> Result<ClimateZone, Error> {
+    let from_hulc = climatezone_from_hulc(&dg.zona_climatica, &dg.comunidad);
+    let from_location = dg
+        .altitud
+        .and_then(|altitud| climatezone_from_location(&dg.provincia, altitud));
+    let from_file: Result<ClimateZone, _> = dg.archivo_climatico.as_str().try_into();
+    let zone = match (from_file, from_hulc, from_location) {
+        (Ok(file_zone), _, _) => file_zone,
+        (Err(_), Some(zone), _) | (Err(_), None, Some(zone)) => {
+            warn!(
+                "Archivo climático {} sin zona climática reconocida. Se usa la zona de la localidad {} ({}, {}): {}",
+                dg.archivo_climatico, dg.localidad, dg.provincia, dg.comunidad, zone
+            );
+            zone
+        }
+        (Err(e), None, None) => return Err(anyhow!("ERROR: {}", e)),
+    };
+    if let Some(hulc_zone) = from_hulc.filter(|z| *z != zone) {
+        warn!(
+            "La zona climática del proyecto ({}) no coincide con la de la localidad {} ({}, {}) indicada en HULC ({})",
+            zone, dg.localidad, dg.provincia, dg.comunidad, hulc_zone
+        );
+    }
+    if let Some(location_zone) = from_location.filter(|z| *z != zone) {
+        warn!(
+            "La zona climática del proyecto ({}) no coincide con la de la localidad {} ({}, {} m) según el DB-HE Anejo B ({})",
+            zone,
+            dg.localidad,
+            dg.provincia,
+            dg.altitud.unwrap_or_default(),
+            location_zone
+        );
+    }
+    Ok(zone)
+}
+
 /// Construye diccionario de espacios a partir de datos BDL (Data)
 fn spaces_from_bdl(bdl: &Data) -> Result<Vec<Space>, Error> {
     bdl.spaces
@@ -148,6 +211,13 @@ fn spaces_from_bdl(bdl: &Data) -> Result<Vec<Space>, Error> {
                     _ => SpaceType::UNCONDITIONED,
                 },
                 n_v: s.airchanges_h,
+                buried_depth: None,
+                crawl_space: None,
+                conditions: if s.spaceconds.is_empty() {
+                    None
+                } else {
+                    Some(s.spaceconds.clone())
+                },
             })
         })
         .collect::<Result<Vec<Space>, Error>>()
@@ -219,6 +289,7 @@ fn thermal_bridges_from_bdl(bdl: &Data) -> Vec<ThermalBridge> {
                 name: tb.name.clone(),
                 l: fround2(tb.length.unwrap_or(0.0)),
                 psi: tb.psi,
+                frsi: Some(tb.frsi),
             }
         })
         .collect()
@@ -236,42 +307,59 @@ fn wallcons_from_bdl(walls: &Vec<Wall>, bdl: &Data) -> Result<Vec<WallCons>, Err
     wcnames
         .iter()
         .map(|wcons| {
-            let wallcons = bdl
-                .db
-                .wallcons
-                .get(wcons)
-                .and_then(|cons|{
-                    let id = uuid_from_obj(wcons);
-                    match cons.r_intrinsic(&bdl.db.materials) {
-                        Ok(r) => Some(WallCons {
-                            id,
-                            name: cons.name.clone(),
-                            group: cons.group.clone(),
-                            thickness: fround2(cons.total_thickness()),
-                            r_intrinsic: fround3(r),
-                            absorptance: cons.absorptance,
-                        }),
-                        _ => {
-                            warn!(
-                                "ERROR: No es posible calcular la R intrínseca de la construcción: {:?}\n",
-                                cons,
-                            );
-                            None
-                        }
-                }})
-                .ok_or_else(|| {
-                    format_err!(
-                        "Construcción de muro no encontrada o incorrecta: '{}'\n",
-                        wcons,
-                    )
-                })?;
-            Ok(wallcons)
+            let cons = bdl.db.wallcons.get(wcons).ok_or_else(|| {
+                format_err!(
+                    "Construcción de muro no encontrada o incorrecta: '{}'\n",
+                    wcons,
+                )
+            })?;
+            wallcons_from_bdl_cons(cons, &bdl.db.materials)
         })
         .collect::<Result<Vec<_>, _>>()
 }
 
+/// Construcción de muro a partir de una composición de capas BDL
+///
+/// El id se obtiene a partir del nombre de la composición
+pub(crate) fn wallcons_from_bdl_cons(
+    cons: &bdl::WallCons,
+    materials: &HashMap<String, bdl::Material>,
+) -> Result<WallCons, Error> {
+    let r = cons.r_intrinsic(materials).map_err(|e| {
+        warn!(
+            "ERROR: No es posible calcular la R intrínseca de la construcción: {:?}\n",
+            cons,
+        );
+        format_err!(
+            "Construcción de muro no encontrada o incorrecta: '{}' ({})\n",
+            cons.name,
+            e
+        )
+    })?;
+    let dynamic = dynamic_properties(cons, materials).unwrap_or_else(|e| {
+        warn!(
+            "No es posible calcular las características dinámicas de la construcción {}: {}",
+            cons.name, e
+        );
+        DynamicProperties::default()
+    });
+    Ok(WallCons {
+        id: uuid_from_obj(&cons.name),
+        name: cons.name.clone(),
+        group: cons.group.clone(),
+        thickness: fround2(cons.total_thickness()),
+        r_intrinsic: fround3(r),
+        absorptance: cons.absorptance,
+        kappa_1: dynamic.kappa_1.round(),
+        kappa_2: dynamic.kappa_2.round(),
+        y_12: fround3(dynamic.y_12),
+        decrement_factor: fround3(dynamic.decrement_factor),
+        time_shift: fround2(dynamic.time_shift),
+    })
+}
+
 /// Construcciones de huecos a partir de datos BDL
-fn windowcons_from_bdl(bdl: &Data) -> Result<Vec<WindowCons>, Error> {
+fn windowcons_from_bdl(bdl: &Data, u_method: WindowUMethod) -> Result<Vec<WindowCons>, Error> {
     let mut wcnames: Vec<String> = bdl
         .windows
         .iter()
@@ -283,47 +371,74 @@ fn windowcons_from_bdl(bdl: &Data) -> Result<Vec<WindowCons>, Error> {
     wcnames
         .iter()
         .map(|wcons| {
-            bdl.db
-                .windowcons
-                .get(wcons)
-                .and_then(|cons| {
-                    let id = uuid_from_obj(cons);
-                    // Vidrio del hueco (Glass)
-                    let glass = match bdl
-                        .db
-                        .glasses
-                        .get(&cons.glass)
-                        .ok_or_else(|| format_err!("Vidrio no encontrado: {}", cons.glass,))
-                    {
-                        Ok(glass) => glass,
-                        _ => return None,
-                    };
-                    let ff = cons.framefrac;
-                    let gglwi = fround2(glass.g_gln * 0.90);
-                    let gglshwi = cons.gglshwi.unwrap_or(gglwi);
-                    let infcoeff_100 = cons.infcoeff;
-                    let u = fround2(cons.u(&bdl.db.frames, &bdl.db.glasses).unwrap_or_default());
-                    Some(WindowCons {
-                        id,
-                        name: cons.name.clone(),
-                        group: cons.group.clone(),
-                        u,
-                        ff,
-                        gglwi,
-                        gglshwi,
-                        infcoeff_100,
-                    })
-                })
-                .ok_or_else(|| {
-                    format_err!(
-                        "Construcción de hueco no encontrada o mal formada: {}",
-                        &wcons,
-                    )
-                })
+            let cons = bdl.db.windowcons.get(wcons).ok_or_else(|| {
+                format_err!(
+                    "Construcción de hueco no encontrada o mal formada: {}",
+                    &wcons,
+                )
+            })?;
+            let u = match u_method {
+                WindowUMethod::HULC => cons.u(&bdl.db.frames, &bdl.db.glasses),
+                WindowUMethod::ISO10077 { psi_g } => windowcons_u_iso10077(bdl, cons, psi_g),
+            };
+            windowcons_from_bdl_cons(cons, &bdl.db, u.unwrap_or_default())
         })
         .collect::<Result<Vec<_>, _>>()
 }
 
+/// Construcción de hueco a partir de una definición de hueco BDL y su transmitancia térmica
+///
+/// El id se obtiene a partir de la definición BDL
+pub(crate) fn windowcons_from_bdl_cons(
+    cons: &bdl::WindowCons,
+    db: &bdl::DB,
+    u: f32,
+) -> Result<WindowCons, Error> {
+    // Vidrio del hueco (Glass)
+    let glass = db.glasses.get(&cons.glass).ok_or_else(|| {
+        format_err!(
+            "Construcción de hueco no encontrada o mal formada: {} (vidrio no encontrado: {})",
+            cons.name,
+            cons.glass
+        )
+    })?;
+    let gglwi = fround2(glass.g_gln * 0.90);
+    Ok(WindowCons {
+        id: uuid_from_obj(cons),
+        name: cons.name.clone(),
+        group: cons.group.clone(),
+        u: fround2(u),
+        ff: cons.framefrac,
+        gglwi,
+        gglshwi: cons.gglshwi.unwrap_or(gglwi),
+        infcoeff_100: cons.infcoeff,
+        shading: None,
+    })
+}
+
+/// U de una construcción de hueco según UNE-EN ISO 10077-1 como media ponderada por superficie
+/// de las U de los huecos que la usan, ya que depende de la geometría de cada hueco
+fn windowcons_u_iso10077(bdl: &Data, cons: &bdl::WindowCons, psi_g: f32) -> Result<f32, Error> {
+    let (mut ua, mut area) = (0.0, 0.0);
+    for win in bdl.windows.iter().filter(|w| w.cons == cons.name) {
+        let u = cons.u_iso10077(
+            &bdl.db.frames,
+            &bdl.db.glasses,
+            win.width,
+            win.height,
+            psi_g,
+        )?;
+        let a = win.width * win.height;
+        ua += u * a;
+        area += a;
+    }
+    if area > 0.0 {
+        Ok(ua / area)
+    } else {
+        cons.u(&bdl.db.frames, &bdl.db.glasses)
+    }
+}
+
 /// Factor de obstáculos remotos (Fshobst) en función del retranqueo, orientación y geometría del hueco
 /// Se calcula, para huecos verticales, de acuerdo a la tabla 17 del DA DB-HE/1 (p. 19).
 /// Es un cálculo best-effort. Podríamos mejorarlo implementando la 52016-1 pero lo puede personalizar el usuario luego
@@ -0,0 +1,226 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Transmisión de calor mensual a través del terreno
+//! - UNE-EN ISO 13370:2017 Anexo A. Flujo de calor mensual a través del terreno: coeficientes periódicos
+//!
+//! Se considera una variación sinusoidal anual de las temperaturas interior y exterior, con el
+//! mínimo en el mes más frío. El flujo de calor de cada mes es:
+//!
+//! Φ_m = H_g · (θ̄_i - θ̄_e) - H_pi · θ̂_i · cos(2π(m - τ + α)/12) + H_pe · θ̂_e · cos(2π(m - τ - β)/12)
+
+#![allow(non_snake_case)]
+
+use std::f32::consts::PI;
+
+use log::debug;
+
+use super::{
+    model_impl::{buried_depth, LAMBDA_GND, LAMBDA_INS, RSE, RSI_DESCENDENTE, RSI_HORIZONTAL},
+    BoundaryType, GroundElementHeatFlow, GroundHeatFlowDetail, Model, ModelIndex, MonthlyClimate,
+    Tilt, Wall,
+};
+
+/// Capacidad calorífica volumétrica del terreno (arena o grava), ρ·c (J/m3K)
+/// UNE-EN ISO 13370:2017, tabla 7
+pub const RHO_C_GND: f32 = 2.0e6;
+
+/// Temperaturas de consigna interiores mensuales por defecto (ºC)
+/// 20ºC en los meses de calefacción y 25ºC de junio a septiembre
+pub const THETA_I_DEFAULT: [f32; 12] = [
+    20.0, 20.0, 20.0, 20.0, 20.0, 25.0, 25.0, 25.0, 25.0, 20.0, 20.0, 20.0,
+];
+
+/// Espesor supuesto de los muros perimetrales (m)
+const W: f32 = 0.3;
+
+/// Profundidad de penetración periódica anual del terreno, δ (m)
+/// UNE-EN ISO 13370:2017, ecuación (A.2)
+pub fn ground_penetration_depth() -> f32 {
+    let period = 3.15e7; // Duración del año (s)
+    f32::sqrt(period * LAMBDA_GND / (PI * RHO_C_GND))
+}
+
+/// Valor medio, amplitud y mes del mínimo [1-12] de una serie mensual
+fn mean_amplitude_min(values: &[f32; 12]) -> (f32, f32, u32) {
+    let mean = values.iter().sum::<f32>() / 12.0;
+    let (max, min) = values
+        .iter()
+        .fold((f32::MIN, f32::MAX), |(mx, mn), &v| (mx.max(v), mn.min(v)));
+    let tau = values
+        .iter()
+        .enumerate()
+        .fold(0, |imin, (i, &v)| if v < values[imin] { i } else { imin });
+    (mean, 0.5 * (max - min), tau as u32 + 1)
+}
+
+/// Coeficiente de transmisión periódico interior de un elemento de espesor equivalente d (W/K)
+/// UNE-EN ISO 13370:2017, ecuaciones (A.3) y (A.9)
+///
+/// H_pi = A · λ / δ · √(2 / ((1 + d/δ)² + 1))
+pub fn h_pi(area: f32, d: f32, delta: f32) -> f32 {
+    area * LAMBDA_GND / delta * f32::sqrt(2.0 / ((1.0 + d / delta).powi(2) + 1.0))
+}
+
+/// Desfases de los flujos interior (α) y exterior (β) de un elemento de espesor equivalente d (meses)
+/// UNE-EN ISO 13370:2017, ecuaciones (A.5) y (A.6)
+fn phase_shifts(d: f32, delta: f32) -> (f32, f32) {
+    let alpha = 1.5 - 12.0 / (2.0 * PI) * f32::atan(d / (d + delta));
+    let beta = 1.5 - 0.42 * f32::ln(delta / (d + 1.0));
+    (alpha, beta)
+}
+
+impl Model {
+    /// Flujo de calor mensual a través del terreno, por elemento y por espacio (W)
+    ///
+    /// climate: temperaturas exteriores medias mensuales (ver climatedata::monthly_climate_from_met)
+    /// theta_i: temperaturas interiores medias mensuales (ºC). Ver THETA_I_DEFAULT
+    ///
+    /// - Soleras y suelos de sótano: UNE-EN ISO 13370:2017 A.2 y A.4, con el aislamiento perimetral
+    ///   horizontal de los metadatos (A.3)
+    /// - Muros de sótano: UNE-EN ISO 13370:2017 A.4, para la parte enterrada
+    /// - Cubiertas enterradas: solo flujo estacionario, ya que el terreno se incluye en la composición
+    ///
+    /// El coeficiente estacionario H_g = U · A usa la U del elemento (incluye el efecto de borde)
+    pub fn monthly_ground_heat_flow(
+        &self,
+        climate: &MonthlyClimate,
+        theta_i: &[f32; 12],
+    ) -> GroundHeatFlowDetail {
+        let delta = ground_penetration_depth();
+        let (theta_e_mean, theta_e_amplitude, tau) = mean_amplitude_min(&climate.temp);
+        let (theta_i_mean, theta_i_amplitude, _) = mean_amplitude_min(theta_i);
+
+        let mut detail = GroundHeatFlowDetail {
+            delta,
+            theta_i_mean,
+            theta_i_amplitude,
+            theta_e_mean,
+            theta_e_amplitude,
+            tau,
+            ..Default::default()
+        };
+
+        let u_values = self.u_values();
+        for wall in self
+            .walls
+            .iter()
+            .filter(|w| w.bounds == BoundaryType::GROUND)
+        {
+            let u = u_values.wall(wall);
+            let mut el = match u
+                .and_then(|u| self.ground_periodic_coefficients(wall, u, delta, &u_values.index))
+            {
+                Some(el) => el,
+                None => continue,
+            };
+            for m in 0..12 {
+                let t = (m + 1) as f32 - tau as f32;
+                el.flow[m] = el.h_g * (theta_i_mean - theta_e_mean)
+                    - el.h_pi * theta_i_amplitude * f32::cos(2.0 * PI * (t + el.alpha) / 12.0)
+                    + el.h_pe * theta_e_amplitude * f32::cos(2.0 * PI * (t - el.beta) / 12.0);
+            }
+            debug!(
+                "{} (flujo por el terreno) H_g={:.2}, H_pi={:.2}, H_pe={:.2}, α={:.2}, β={:.2}, Φ={:?}",
+                wall.name, el.h_g, el.h_pi, el.h_pe, el.alpha, el.beta, el.flow
+            );
+            let space_flow = detail.by_space.entry(wall.space.clone()).or_default();
+            for ((s, t), f) in space_flow
+                .iter_mut()
+                .zip(detail.total.iter_mut())
+                .zip(&el.flow)
+            {
+                *s += f;
+                *t += f;
+            }
+            detail.elements.push(el);
+        }
+        detail
+    }
+
+    /// Coeficientes de transmisión estacionario y periódicos de un elemento en contacto con el terreno
+    /// u es la transmitancia térmica del elemento (W/m2K)
+    fn ground_periodic_coefficients(
+        &self,
+        wall: &Wall,
+        u: f32,
+        delta: f32,
+        index: &ModelIndex,
+    ) -> Option<GroundElementHeatFlow> {
+        let space = index.get_wallspace(wall)?;
+        let cons = index.get_wallcons(wall)?;
+        let h_g = u * wall.area;
+        let z = buried_depth(space);
+
+        let mut el = GroundElementHeatFlow {
+            id: wall.id.clone(),
+            name: wall.name.clone(),
+            space: wall.space.clone(),
+            h_g,
+            ..Default::default()
+        };
+
+        match Tilt::from(wall) {
+            // Suelos sobre cámara sanitaria: solo flujo estacionario
+            Tilt::BOTTOM if space.crawl_space.is_some() => (),
+            Tilt::BOTTOM => {
+                // Perímetro expuesto del espacio, repartido en proporción a la superficie del suelo
+                let space_P = space
+                    .exposed_perimeter
+                    .unwrap_or_else(|| 4.0 * f32::sqrt(space.area));
+                let P = if space.area > 0.0 {
+                    space_P * wall.area / space.area
+                } else {
+                    0.0
+                };
+                let d_t = W + LAMBDA_GND * (RSI_DESCENDENTE + cons.r_intrinsic + RSE);
+
+                // Aislamiento perimetral horizontal (A.3): espesor adicional d' = R' · λ
+                let D = self.meta.d_perim_insulation;
+                let R_n = self.meta.rn_perim_insulation;
+                let ln_term = if D > 0.0 && R_n > 0.0 {
+                    let d_1 = R_n * (LAMBDA_GND - LAMBDA_INS);
+                    let e = f32::exp(-D / delta);
+                    (1.0 - e) * f32::ln(delta / (d_t + d_1) + 1.0) + e * f32::ln(delta / d_t + 1.0)
+                } else {
+                    f32::ln(delta / d_t + 1.0)
+                };
+
+                el.h_pi = h_pi(wall.area, d_t, delta);
+                // Suelos de sótano: atenuación con la profundidad (A.4)
+                el.h_pe = 0.37 * P * LAMBDA_GND * f32::exp(-z / delta) * ln_term;
+                let (alpha, beta) = phase_shifts(d_t, delta);
+                el.alpha = alpha;
+                el.beta = beta;
+            }
+            Tilt::SIDE if z > 0.01 => {
+                // Parte enterrada del muro, suponiendo la altura del espacio
+                let d_w = LAMBDA_GND * (RSI_HORIZONTAL + cons.r_intrinsic + RSE);
+                let buried_fraction = if space.height > 0.0 {
+                    (z / space.height).min(1.0)
+                } else {
+                    1.0
+                };
+                let L = if space.height > 0.0 {
+                    wall.area / space.height
+                } else {
+                    0.0
+                };
+                el.h_pi = h_pi(wall.area * buried_fraction, d_w, delta);
+                el.h_pe = 0.37
+                    * L
+                    * LAMBDA_GND
+                    * 2.0
+                    * (1.0 - f32::exp(-z / delta))
+                    * f32::ln(delta / d_w + 1.0);
+                let (alpha, beta) = phase_shifts(d_w, delta);
+                el.alpha = alpha;
+                el.beta = beta;
+            }
+            // Muros no enterrados y cubiertas enterradas: solo flujo estacionario
+            _ => (),
+        }
+        Some(el)
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Carga térmica de diseño de calefacción por espacio
+//! - UNE-EN 12831-1:2017 Eficiencia energética de los edificios. Método para el cálculo de la carga
+//!   térmica de diseño. Parte 1: Carga térmica de los espacios
+//!
+//! Φ_HL = Φ_T + Φ_V + Φ_RH, con:
+//! - Φ_T: transmisión por opacos y huecos al exterior, al terreno y a espacios adyacentes
+//! - Φ_V: ventilación e infiltraciones (ver Model::H_ve)
+//! - Φ_RH: recuperación tras periodos de apagado o reducción de la consigna
+
+use std::collections::HashMap;
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    model_impl::r_f_interior, BoundaryType::*, HeatLoadDetail, HeatLoadElement, Model,
+    MonthlyClimate, Space, SpaceHeatLoad, SpaceType, Tilt, UValues,
+};
+
+/// Temperatura interior de diseño de los espacios acondicionados (ºC)
+pub const THETA_INT_HEATING: f32 = 20.0;
+
+/// Factor de corrección de la variación anual de la temperatura exterior para el terreno, f_g1 (-)
+pub const F_G1: f32 = 1.45;
+
+/// Factor de corrección del nivel freático para el terreno, G_w (-)
+/// 1.0 si el nivel freático está a más de 1 m por debajo del suelo y 1.15 en otro caso
+pub const G_W: f32 = 1.0;
+
+/// Potencia de recuperación por unidad de superficie, f_RH (W/m2)
+/// Valor orientativo para una reducción nocturna de 2K y un tiempo de recuperación de 2h
+pub const REHEAT_FACTOR_DEFAULT: f32 = 11.0;
+
+/// Condiciones exteriores de diseño de calefacción de la localidad (UNE-EN 12831-1, 6.3.2)
+///
+/// Se toman de los datos climáticos de proyecto de la localidad (p.e. temperatura seca del
+/// percentil 99% de la guía técnica de condiciones climáticas exteriores de proyecto)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct HeatingDesignConditions {
+    /// Temperatura exterior de diseño, θ_e (ºC)
+    pub theta_e: f32,
+    /// Temperatura exterior media anual, θ_m,e (ºC)
+    pub theta_m_e: f32,
+}
+
+impl HeatingDesignConditions {
+    /// Condiciones con la temperatura exterior de diseño dada y la temperatura media anual de las
+    /// temperaturas exteriores medias mensuales
+    pub fn new(theta_e: f32, climate: &MonthlyClimate) -> Self {
+        HeatingDesignConditions {
+            theta_e,
+            theta_m_e: climate.temp.iter().sum::<f32>() / 12.0,
+        }
+    }
+}
+
+impl Model {
+    /// Carga térmica de diseño de calefacción de los espacios acondicionados (W)
+    ///
+    /// conditions: temperaturas exteriores de diseño y media anual de la localidad
+    /// reheat_factor: potencia de recuperación por unidad de superficie, f_RH (W/m2). Ver REHEAT_FACTOR_DEFAULT
+    ///
+    /// - los elementos en contacto con el terreno se corrigen con f_g1, f_g2 y G_w
+    /// - la temperatura de los espacios no acondicionados adyacentes se estima con su b_tr
+    /// - la carga del edificio tiene en cuenta los multiplicadores de los espacios
+    pub fn design_heat_load(
+        &self,
+        conditions: &HeatingDesignConditions,
+        reheat_factor: f32,
+    ) -> HeatLoadDetail {
+        self.design_heat_load_with(conditions, reheat_factor, &self.u_values())
+    }
+
+    /// design_heat_load con las transmitancias térmicas ya calculadas (ver Model::u_values)
+    pub fn design_heat_load_with(
+        &self,
+        conditions: &HeatingDesignConditions,
+        reheat_factor: f32,
+        u_values: &UValues,
+    ) -> HeatLoadDetail {
+        let hve = self.H_ve_with(u_values);
+        let HeatingDesignConditions { theta_e, theta_m_e } = *conditions;
+        let theta_int = THETA_INT_HEATING;
+        let delta_t = theta_int - theta_e;
+        // Factor de corrección de la diferencia entre temperatura exterior media anual y de diseño, f_g2
+        let f_g2 = (theta_int - theta_m_e) / delta_t;
+
+        let btrs = self.btr_of_spaces(u_values);
+
+        let mut detail = HeatLoadDetail {
+            theta_e,
+            theta_m_e,
+            ..Default::default()
+        };
+
+        for space in self
+            .spaces
+            .iter()
+            .filter(|s| s.inside_tenv && s.space_type == SpaceType::CONDITIONED)
+        {
+            let elements = self.heat_load_elements(space, u_values, &btrs, theta_e, f_g2);
+            let phi_t = elements.iter().map(|e| e.phi).sum::<f32>();
+            // H_ve incluye el multiplicador del espacio
+            let h_ve = hve.by_space.get(&space.id).map(|p| p.h_ve).unwrap_or(0.0);
+            let phi_v = if space.multiplier > 0.0 {
+                h_ve * delta_t / space.multiplier
+            } else {
+                0.0
+            };
+            let phi_rh = space.area * reheat_factor;
+            let phi_hl = phi_t + phi_v + phi_rh;
+            debug!(
+                "{} Φ_HL={:.0} W (Φ_T={:.0} W, Φ_V={:.0} W, Φ_RH={:.0} W)",
+                space.name, phi_hl, phi_t, phi_v, phi_rh
+            );
+            detail.phi_hl += phi_hl * space.multiplier;
+            detail.spaces.push(SpaceHeatLoad {
+                id: space.id.clone(),
+                name: space.name.clone(),
+                theta_int,
+                phi_t,
+                phi_v,
+                phi_rh,
+                phi_hl,
+                elements,
+            });
+        }
+        info!(
+            "Carga térmica de diseño Φ_HL={:.0} W (θ_e={:.1}ºC, θ_m,e={:.1}ºC)",
+            detail.phi_hl, theta_e, theta_m_e
+        );
+        detail
+    }
+
+    /// Pérdidas por transmisión de los opacos y huecos de un espacio acondicionado
+    fn heat_load_elements(
+        &self,
+        space: &Space,
+        u_values: &UValues,
+        btrs: &HashMap<String, f32>,
+        theta_e: f32,
+        f_g2: f32,
+    ) -> Vec<HeatLoadElement> {
+        let theta_int = THETA_INT_HEATING;
+        let delta_t = theta_int - theta_e;
+        let mut elements = Vec::new();
+
+        for wall in u_values.index.walls_of_space(&space.id) {
+            // Factor de corrección de temperatura (f), temperatura al otro lado y U del opaco
+            let (f, theta_adj, u) = match wall.bounds {
+                ADIABATIC => continue,
+                EXTERIOR if wall.space == space.id => (1.0, theta_e, u_values.wall(wall)),
+                GROUND if wall.space == space.id => {
+                    (F_G1 * f_g2 * G_W, theta_e, u_values.wall(wall))
+                }
+                INTERIOR => {
+                    let thiscondspace = wall.space == space.id;
+                    let otherid = if thiscondspace {
+                        wall.nextto.as_deref()
+                    } else {
+                        Some(wall.space.as_str())
+                    };
+                    let other = match otherid.and_then(|id| u_values.index.get_space(id)) {
+                        Some(other) => other,
+                        None => continue,
+                    };
+                    // Espacios acondicionados a la misma temperatura
+                    if other.space_type == SpaceType::CONDITIONED {
+                        continue;
+                    }
+                    let btr = match btrs.get(&other.id) {
+                        Some(btr) => *btr,
+                        None => continue,
+                    };
+                    let u = u_values.index.get_wallcons(wall).map(|cons| {
+                        1.0 / r_f_interior(Tilt::from(wall), thiscondspace, cons.r_intrinsic)
+                    });
+                    (btr, theta_int - btr * delta_t, u)
+                }
+                _ => continue,
+            };
+            let u = match u {
+                Some(u) => u,
+                None => continue,
+            };
+            elements.push(HeatLoadElement {
+                id: wall.id.clone(),
+                name: wall.name.clone(),
+                area: wall.area,
+                u,
+                f,
+                theta_adj,
+                phi: wall.area * u * f * delta_t,
+            });
+            for win in u_values.index.windows_of_wall(&wall.id) {
+                if let Some(u) = u_values.window(win) {
+                    elements.push(HeatLoadElement {
+                        id: win.id.clone(),
+                        name: win.name.clone(),
+                        area: win.area,
+                        u,
+                        f,
+                        theta_adj,
+                        phi: win.area * u * f * delta_t,
+                    });
+                }
+            }
+        }
+        elements
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Coeficientes de transferencia de calor por transmisión y ventilación del edificio
+//! - UNE-EN ISO 13789:2017 Prestaciones térmicas de edificios. Coeficientes de transferencia de
+//!   calor por transmisión y por renovación de aire
+//!
+//! Se consideran los espacios interiores a la envolvente térmica. Los valores se dan por espacio
+//! y por zona térmica, que agrupa los espacios con las mismas condiciones operacionales
+//! (ver zone_of_space).
+
+#![allow(non_snake_case)]
+
+use std::collections::BTreeMap;
+
+use log::info;
+
+use super::{
+    BoundaryType::*, HtrDetail, HveDetail, HveParts, Model, ModelIndex, Space, SpaceType, UValues,
+    Wall,
+};
+
+/// Zona térmica de los espacios sin condiciones operacionales definidas
+pub const ZONE_DEFAULT: &str = "Sin condiciones";
+
+/// Zona térmica de un espacio, según sus condiciones operacionales (Space.conditions)
+pub fn zone_of_space(space: &Space) -> &str {
+    space.conditions.as_deref().unwrap_or(ZONE_DEFAULT)
+}
+
+impl Model {
+    /// Coeficiente de transferencia de calor por transmisión del edificio, H_tr (W/K)
+    ///
+    /// H_tr = H_D + H_g + H_U + H_tb, donde:
+    /// - H_D: opacos y huecos en contacto con el aire exterior
+    /// - H_g: opacos en contacto con el terreno (régimen estacionario)
+    /// - H_U: opacos y huecos en contacto con espacios no acondicionados fuera de la envolvente,
+    ///   con la U equivalente que incluye el efecto de esos espacios (b_tr)
+    /// - H_tb: puentes térmicos
+    ///
+    /// Los puentes térmicos no están asignados a espacios, por lo que se reparten entre estos en
+    /// proporción a su superficie de intercambio con el exterior y el terreno.
+    /// Se tienen en cuenta los multiplicadores de los espacios.
+    pub fn H_tr(&self) -> HtrDetail {
+        self.H_tr_with(&self.u_values())
+    }
+
+    /// H_tr con las transmitancias térmicas ya calculadas (ver Model::u_values)
+    pub fn H_tr_with(&self, u_values: &UValues) -> HtrDetail {
+        let mut detail = HtrDetail::default();
+        let mut envelope_area = BTreeMap::<String, f32>::new();
+
+        for wall in &self.walls {
+            let (spaceid, multiplier) = match envelope_space_of_wall(wall, &u_values.index) {
+                Some(s) => s,
+                None => continue,
+            };
+            let h = match wall_a_u_with_windows(wall, u_values) {
+                Some(h) => h * multiplier,
+                None => continue,
+            };
+            let parts = detail.by_space.entry(spaceid.to_string()).or_default();
+            match wall.bounds {
+                EXTERIOR => parts.h_d += h,
+                GROUND => parts.h_g += h,
+                INTERIOR => parts.h_u += h,
+                ADIABATIC => (),
+            }
+            if wall.bounds == EXTERIOR || wall.bounds == GROUND {
+                *envelope_area.entry(spaceid.to_string()).or_default() += wall.area * multiplier;
+            }
+        }
+
+        let h_tb: f32 = self.thermal_bridges.iter().map(|tb| tb.psi * tb.l).sum();
+        let total_area: f32 = envelope_area.values().sum();
+        for (spaceid, parts) in detail.by_space.iter_mut() {
+            if total_area > 0.0 {
+                parts.h_tb = h_tb * envelope_area.get(spaceid).unwrap_or(&0.0) / total_area;
+            }
+            parts.h_tr = parts.h_d + parts.h_g + parts.h_u + parts.h_tb;
+        }
+
+        for (spaceid, parts) in &detail.by_space {
+            let zone = match u_values.index.get_space(spaceid) {
+                Some(space) => zone_of_space(space),
+                None => continue,
+            };
+            let zone_parts = detail.by_zone.entry(zone.to_string()).or_default();
+            zone_parts.h_d += parts.h_d;
+            zone_parts.h_g += parts.h_g;
+            zone_parts.h_u += parts.h_u;
+            zone_parts.h_tb += parts.h_tb;
+            zone_parts.h_tr += parts.h_tr;
+        }
+
+        let total = &mut detail.total;
+        for parts in detail.by_space.values() {
+            total.h_d += parts.h_d;
+            total.h_g += parts.h_g;
+            total.h_u += parts.h_u;
+        }
+        // Se conservan los puentes térmicos aunque no haya superficies a las que asignarlos
+        total.h_tb = h_tb;
+        total.h_tr = total.h_d + total.h_g + total.h_u + total.h_tb;
+
+        info!(
+            "H_tr={:.2} W/K (H_D={:.2} W/K, H_g={:.2} W/K, H_U={:.2} W/K, H_tb={:.2} W/K)",
+            total.h_tr, total.h_d, total.h_g, total.h_u, total.h_tb
+        );
+        detail
+    }
+
+    /// Coeficiente de transferencia de calor por ventilación e infiltraciones del edificio, H_ve (W/K)
+    ///
+    /// H_ve = 0.33 · (q_ve + q_inf), con los caudales en m3/h:
+    /// - ventilación: n_v de cada espacio o, en espacios habitables de uso residencial sin n_v,
+    ///   la ventilación global del edificio repartida en proporción al volumen
+    /// - infiltraciones: n_inf = n50 / 20, con la n50 del ensayo o la de referencia del DB-HE
+    pub fn H_ve(&self) -> HveDetail {
+        self.H_ve_with(&self.u_values())
+    }
+
+    /// H_ve usando los índices de los elementos ya calculados (ver Model::u_values)
+    pub fn H_ve_with(&self, u_values: &UValues) -> HveDetail {
+        let index = &u_values.index;
+        let n50 = self.n50_with(index);
+        let n_inf = n50 / 20.0;
+        let vol_inh = self.vol_env_inh_net_with(index);
+        let mut detail = HveDetail {
+            n50,
+            n_inf,
+            ..Default::default()
+        };
+
+        for space in self.spaces.iter().filter(|s| s.inside_tenv) {
+            let volume = space.area
+                * (space.height - self.top_wall_thickness_with(&space.id, index))
+                * space.multiplier;
+            let q_ve = match (space.n_v, self.meta.global_ventilation_l_s) {
+                (Some(n_v), _) => n_v * volume,
+                (None, Some(global))
+                    if space.space_type != SpaceType::UNINHABITED && vol_inh > 0.0 =>
+                {
+                    3.6 * global * volume / vol_inh
+                }
+                _ => 0.0,
+            };
+            let q_inf = n_inf * volume;
+            let parts = HveParts {
+                volume,
+                q_ve,
+                q_inf,
+                h_ve: 0.33 * (q_ve + q_inf),
+            };
+            for sum in [
+                &mut detail.total,
+                detail
+                    .by_zone
+                    .entry(zone_of_space(space).to_string())
+                    .or_default(),
+            ] {
+                sum.volume += parts.volume;
+                sum.q_ve += parts.q_ve;
+                sum.q_inf += parts.q_inf;
+                sum.h_ve += parts.h_ve;
+            }
+            detail.by_space.insert(space.id.clone(), parts);
+        }
+
+        info!(
+            "H_ve={:.2} W/K (q_ve={:.2} m³/h, q_inf={:.2} m³/h, n50={:.2} 1/h, V={:.2} m³)",
+            detail.total.h_ve, detail.total.q_ve, detail.total.q_inf, n50, detail.total.volume
+        );
+        detail
+    }
+}
+
+/// Espacio de la envolvente térmica (id y multiplicador) al que se asigna la transmisión de un opaco
+///
+/// Los elementos interiores se asignan al espacio interior a la envolvente cuando comunican con
+/// un espacio fuera de ella
+fn envelope_space_of_wall<'a>(wall: &Wall, index: &ModelIndex<'a>) -> Option<(&'a str, f32)> {
+    let space = index.get_wallspace(wall)?;
+    match wall.bounds {
+        EXTERIOR | GROUND if space.inside_tenv => Some((&space.id, space.multiplier)),
+        INTERIOR => {
+            let nextspace = index.get_space(wall.nextto.as_ref()?)?;
+            match (space.inside_tenv, nextspace.inside_tenv) {
+                (true, false) => Some((&space.id, space.multiplier)),
+                (false, true) => Some((&nextspace.id, nextspace.multiplier)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// A·U de un opaco y de sus huecos (W/K)
+fn wall_a_u_with_windows(wall: &Wall, u_values: &UValues) -> Option<f32> {
+    let wall_a_u = u_values.wall(wall)? * wall.area;
+    let windows_a_u: f32 = u_values
+        .index
+        .windows_of_wall(&wall.id)
+        .filter_map(|win| Some(u_values.window(win)? * win.area))
+        .sum();
+    Some(wall_a_u + windows_a_u)
+}
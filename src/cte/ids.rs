@@ -0,0 +1,116 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Identificadores estables de los elementos del modelo
+//!
+//! Los id son UUIDv5 obtenidos a partir de un espacio de nombres propio de cada proyecto, el tipo
+//! de elemento y el nombre del elemento. De este modo se mantienen al volver a importar un proyecto
+//! modificado y no coinciden entre proyectos distintos.
+//!
+//! Al importar un proyecto desde su archivo .ctehexml (collect_hulc_data) el espacio de nombres se obtiene
+//! a partir de la ruta absoluta del archivo, y se guarda en Meta::id_namespace. Así, los proyectos con el
+//! mismo nombre en distintas carpetas no comparten id y estos no cambian al renombrar el proyecto en HULC,
+//! aunque sí al mover o renombrar el archivo. Para conservarlos en ese caso se debe copiar
+//! Meta::id_namespace del modelo anterior antes de llamar a Model::set_named_ids.
+//!
+//! Los modelos sin espacio de nombres guardado (p.e. los importados sin conocer la ruta del archivo)
+//! usan el obtenido a partir del nombre del proyecto, que coincide entre proyectos con el mismo nombre
+//! y cambia al renombrarlo.
+//!
+//! Tipos de elemento: Space, Wall, Window, ThermalBridge, WallCons, WindowCons
+
+use std::{collections::HashMap, path::Path};
+
+use log::debug;
+use uuid::Uuid;
+
+use super::Model;
+use crate::utils::{uuid_from_name, UUID_NAMESPACE};
+
+impl Model {
+    /// Espacio de nombres UUID del proyecto
+    ///
+    /// Es el guardado en los metadatos o, si no existe, el obtenido a partir del nombre del proyecto,
+    /// que puede coincidir entre proyectos distintos
+    pub fn id_namespace(&self) -> Uuid {
+        self.meta
+            .id_namespace
+            .unwrap_or_else(|| Uuid::new_v5(&UUID_NAMESPACE, self.meta.name.as_bytes()))
+    }
+
+    /// Guarda el espacio de nombres UUID obtenido a partir de la ruta del archivo .ctehexml del proyecto
+    ///
+    /// Se usa la ruta absoluta, si puede obtenerse. Los id deben actualizarse luego con Model::set_named_ids
+    pub fn set_id_namespace_from_path<T: AsRef<Path>>(&mut self, path: T) {
+        let path = path.as_ref();
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let ns = Uuid::new_v5(&UUID_NAMESPACE, path.to_string_lossy().as_bytes());
+        self.meta.id_namespace = Some(ns);
+    }
+
+    /// Id de un elemento del proyecto a partir de su tipo y nombre
+    pub fn named_id(&self, kind: &str, name: &str) -> String {
+        uuid_from_name(&self.id_namespace(), kind, name)
+    }
+
+    /// Asigna a todos los elementos id basados en su nombre y actualiza las referencias entre elementos
+    ///
+    /// Guarda en los metadatos el espacio de nombres usado, si no estaba definido
+    pub fn set_named_ids(&mut self) {
+        let ns = self.id_namespace();
+        self.meta.id_namespace = Some(ns);
+        let mut spaces = HashMap::new();
+        for s in self.spaces.iter_mut() {
+            let id = uuid_from_name(&ns, "Space", &s.name);
+            spaces.insert(std::mem::replace(&mut s.id, id.clone()), id);
+        }
+        let mut wallcons = HashMap::new();
+        for c in self.wallcons.iter_mut() {
+            let id = uuid_from_name(&ns, "WallCons", &c.name);
+            wallcons.insert(std::mem::replace(&mut c.id, id.clone()), id);
+        }
+        let mut wincons = HashMap::new();
+        for c in self.wincons.iter_mut() {
+            let id = uuid_from_name(&ns, "WindowCons", &c.name);
+            wincons.insert(std::mem::replace(&mut c.id, id.clone()), id);
+        }
+        let mut walls = HashMap::new();
+        for w in self.walls.iter_mut() {
+            let id = uuid_from_name(&ns, "Wall", &w.name);
+            walls.insert(std::mem::replace(&mut w.id, id.clone()), id);
+            update_ref(&mut w.space, &spaces);
+            update_ref(&mut w.cons, &wallcons);
+            if let Some(nextto) = w.nextto.as_mut() {
+                update_ref(nextto, &spaces);
+            }
+        }
+        for w in self.windows.iter_mut() {
+            w.id = uuid_from_name(&ns, "Window", &w.name);
+            update_ref(&mut w.wall, &walls);
+            update_ref(&mut w.cons, &wincons);
+        }
+        for tb in self.thermal_bridges.iter_mut() {
+            tb.id = uuid_from_name(&ns, "ThermalBridge", &tb.name);
+        }
+        if let Some(extra) = self.extra.as_mut() {
+            for e in extra.iter_mut() {
+                update_ref(&mut e.cons, &wallcons);
+                if let Some(nextspace) = e.nextspace.as_mut() {
+                    update_ref(nextspace, &spaces);
+                }
+            }
+        }
+        debug!(
+            "Asignados id basados en nombres con el espacio de nombres {}",
+            ns
+        );
+    }
+}
+
+/// Sustituye un id por el nuevo id correspondiente, si existe
+fn update_ref(id: &mut String, ids: &HashMap<String, String>) {
+    if let Some(new_id) = ids.get(id.as_str()) {
+        *id = new_id.clone();
+    }
+}
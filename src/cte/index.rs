@@ -0,0 +1,177 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Índices de los elementos del modelo para búsquedas sin recorrer las listas de elementos
+//!
+//! Incluye la posición de cada elemento según su id y las listas de huecos de cada opaco y de
+//! opacos de cada espacio (por espacio o espacio adyacente), de modo que las búsquedas en
+//! bucles anidados (U de opacos, K, compacidad...) no tienen coste lineal.
+//!
+//! La estructura toma prestado el modelo, de modo que no puede usarse tras modificarlo y debe
+//! volver a generarse.
+
+use std::collections::HashMap;
+
+use log::warn;
+
+use super::{Model, Space, Wall, WallCons, Window, WindowCons};
+
+/// Índices de los elementos del modelo
+#[derive(Debug, Clone, Default)]
+pub struct ModelIndex<'a> {
+    spaces: &'a [Space],
+    walls: &'a [Wall],
+    windows: &'a [Window],
+    wallcons: &'a [WallCons],
+    wincons: &'a [WindowCons],
+    /// Posición de los espacios, por id
+    space_pos: HashMap<&'a str, usize>,
+    /// Posición de los opacos, por id
+    wall_pos: HashMap<&'a str, usize>,
+    /// Posición de las construcciones de opacos, por id
+    wallcons_pos: HashMap<&'a str, usize>,
+    /// Posición de las construcciones de huecos, por id
+    wincons_pos: HashMap<&'a str, usize>,
+    /// Posición de los huecos de cada opaco, por id del opaco
+    wall_windows: HashMap<&'a str, Vec<usize>>,
+    /// Posición de los opacos de cada espacio o adyacentes a él, por id del espacio
+    space_walls: HashMap<&'a str, Vec<usize>>,
+}
+
+/// Posición de cada elemento por id
+///
+/// Con id repetidos se usa el primer elemento, como en las búsquedas de Model
+fn positions<'a, T>(elements: &'a [T], id: impl Fn(&'a T) -> &'a str) -> HashMap<&'a str, usize> {
+    let mut pos = HashMap::with_capacity(elements.len());
+    for (i, e) in elements.iter().enumerate() {
+        pos.entry(id(e)).or_insert(i);
+    }
+    pos
+}
+
+impl<'a> ModelIndex<'a> {
+    /// Genera los índices de los elementos del modelo
+    pub fn new(model: &'a Model) -> Self {
+        let mut wall_windows: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, win) in model.windows.iter().enumerate() {
+            wall_windows.entry(win.wall.as_str()).or_default().push(i);
+        }
+        let mut space_walls: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, wall) in model.walls.iter().enumerate() {
+            space_walls.entry(wall.space.as_str()).or_default().push(i);
+            if let Some(nextto) = wall.nextto.as_deref() {
+                if nextto != wall.space {
+                    space_walls.entry(nextto).or_default().push(i);
+                }
+            }
+        }
+
+        ModelIndex {
+            spaces: &model.spaces,
+            walls: &model.walls,
+            windows: &model.windows,
+            wallcons: &model.wallcons,
+            wincons: &model.wincons,
+            space_pos: positions(&model.spaces, |s| s.id.as_str()),
+            wall_pos: positions(&model.walls, |w| w.id.as_str()),
+            wallcons_pos: positions(&model.wallcons, |c| c.id.as_str()),
+            wincons_pos: positions(&model.wincons, |c| c.id.as_str()),
+            wall_windows,
+            space_walls,
+        }
+    }
+
+    /// Localiza espacio
+    pub fn get_space(&self, spaceid: &str) -> Option<&'a Space> {
+        self.space_pos.get(spaceid).map(|&i| &self.spaces[i])
+    }
+
+    /// Localiza opaco
+    pub fn get_wall(&self, wallid: &str) -> Option<&'a Wall> {
+        self.wall_pos.get(wallid).map(|&i| &self.walls[i])
+    }
+
+    /// Localiza espacio de opaco
+    pub fn get_wallspace(&self, wall: &Wall) -> Option<&'a Space> {
+        let maybespace = self.get_space(&wall.space);
+        if maybespace.is_none() {
+            warn!(
+                "Muro {} ({}) con definición de espacio incorrecta {}",
+                wall.id, wall.name, wall.space
+            );
+        }
+        maybespace
+    }
+
+    /// Localiza construcción de opaco
+    pub fn get_wallcons(&self, wall: &Wall) -> Option<&'a WallCons> {
+        let maybecons = self
+            .wallcons_pos
+            .get(wall.cons.as_str())
+            .map(|&i| &self.wallcons[i]);
+        if maybecons.is_none() {
+            warn!(
+                "Muro {} ({}) con definición de construcción incorrecta {}",
+                wall.id, wall.name, wall.cons
+            );
+        }
+        maybecons
+    }
+
+    /// Localiza opaco de un hueco
+    pub fn get_windowwall(&self, win: &Window) -> Option<&'a Wall> {
+        let maybewall = self.get_wall(&win.wall);
+        if maybewall.is_none() {
+            warn!(
+                "Hueco {} ({}) con definición de muro incorrecta {}",
+                win.id, win.name, win.wall
+            );
+        }
+        maybewall
+    }
+
+    /// Localiza construcción de hueco
+    pub fn get_wincons(&self, win: &Window) -> Option<&'a WindowCons> {
+        let maybecons = self
+            .wincons_pos
+            .get(win.cons.as_str())
+            .map(|&i| &self.wincons[i]);
+        if maybecons.is_none() {
+            warn!(
+                "Hueco {}({}) con definición de construcción incorrecta {}",
+                win.id, win.name, win.cons
+            );
+        }
+        maybecons
+    }
+
+    /// Iterador de los huecos pertenecientes a un muro
+    pub fn windows_of_wall(&self, wallid: &str) -> impl Iterator<Item = &'a Window> + '_ {
+        let windows = self.windows;
+        self.wall_windows
+            .get(wallid)
+            .map(|v| v.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(move |&i| &windows[i])
+    }
+
+    /// Iterador de los cerramientos (incluyendo muros, suelos y techos) que delimitan un espacio
+    pub fn walls_of_space(&self, spaceid: &str) -> impl Iterator<Item = &'a Wall> + '_ {
+        let walls = self.walls;
+        self.space_walls
+            .get(spaceid)
+            .map(|v| v.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(move |&i| &walls[i])
+    }
+}
+
+impl Model {
+    /// Índices de los elementos del modelo
+    pub fn index(&self) -> ModelIndex<'_> {
+        ModelIndex::new(self)
+    }
+}
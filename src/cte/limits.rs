@@ -0,0 +1,217 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Valores límite de la envolvente térmica del DB-HE 2019 (HE1)
+//! - Tabla 3.1.1.a - HE1: Valores límite de transmitancia térmica, U_lim
+//! - Tablas 3.1.1.b y 3.1.1.c - HE1: Valor límite de la transmitancia térmica global, K_lim
+//! - Tabla 3.1.2 - HE1: Valor límite del parámetro de control solar, q_sol;jul,lim
+//! - Tabla 3.1.3.a - HE1: Valor límite de la relación de cambio de aire a 50 Pa, n_50,lim
+//! - Tabla 3.1.3.b - HE1: Valores límite de permeabilidad al aire de huecos, Q_100,lim
+
+#![allow(non_snake_case)]
+
+use serde::{Deserialize, Serialize};
+
+use super::{BoundaryType, ClimateZone, Model, ModelIndex, Tilt, Wall};
+
+/// Tipos de elementos de la envolvente térmica con valor límite de transmitancia (Tabla 3.1.1.a - HE1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ULimKind {
+    /// Muros y suelos en contacto con el aire exterior, U_S, U_M
+    UM,
+    /// Cubiertas en contacto con el aire exterior, U_C
+    UC,
+    /// Muros, suelos y cubiertas en contacto con espacios no habitables o con el terreno, U_T
+    UT,
+    /// Medianerías o particiones interiores pertenecientes a la envolvente térmica, U_MD
+    UMD,
+    /// Huecos (conjunto de marco, vidrio y, en su caso, cajón de persiana), U_H
+    UH,
+    /// Puertas con superficie semitransparente igual o inferior al 50%
+    DOOR,
+}
+
+/// Índice de la zona climática de invierno (α, A, B, C, D, E)
+fn winter_index(climate: &ClimateZone) -> usize {
+    use ClimateZone::*;
+    match climate {
+        Alfa1c | Alfa2c | Alfa3c | Alfa4c => 0,
+        A1c | A2c | A3c | A4c | A3 | A4 => 1,
+        B1c | B2c | B3c | B4c | B3 | B4 => 2,
+        C1c | C2c | C3c | C4c | C1 | C2 | C3 | C4 => 3,
+        D1c | D2c | D3c | D1 | D2 | D3 => 4,
+        E1c | E1 => 5,
+    }
+}
+
+/// Zona climática de verano (1, 2, 3, 4)
+pub(crate) fn summer_zone(climate: &ClimateZone) -> u8 {
+    use ClimateZone::*;
+    match climate {
+        Alfa1c | A1c | B1c | C1c | D1c | E1c | C1 | D1 | E1 => 1,
+        Alfa2c | A2c | B2c | C2c | D2c | C2 | D2 => 2,
+        Alfa3c | A3c | B3c | C3c | D3c | A3 | B3 | C3 | D3 => 3,
+        Alfa4c | A4c | B4c | C4c | A4 | B4 | C4 => 4,
+    }
+}
+
+/// Transmitancia térmica límite de un tipo de elemento para una zona climática, U_lim (W/m2K)
+/// Tabla 3.1.1.a - HE1
+pub fn u_lim(climate: &ClimateZone, kind: ULimKind) -> f32 {
+    use ULimKind::*;
+    #[rustfmt::skip]
+    let values: [f32; 6] = match kind {
+        //    α     A     B     C     D     E
+        UM => [0.80, 0.70, 0.56, 0.49, 0.41, 0.37],
+        UC => [0.55, 0.50, 0.44, 0.40, 0.35, 0.33],
+        UT => [0.90, 0.80, 0.75, 0.70, 0.65, 0.59],
+        UMD => [1.35, 1.25, 1.10, 0.95, 0.85, 0.80],
+        UH => [3.20, 2.70, 2.30, 2.10, 1.80, 1.80],
+        DOOR => [5.70, 5.70, 5.70, 5.70, 5.70, 5.70],
+    };
+    values[winter_index(climate)]
+}
+
+/// Permeabilidad al aire límite de los huecos a 100 Pa, Q_100,lim (m3/hm2)
+/// Tabla 3.1.3.b - HE1
+pub fn c_100_lim(climate: &ClimateZone) -> f32 {
+    match winter_index(climate) {
+        0..=2 => 27.0,
+        _ => 9.0,
+    }
+}
+
+/// Interpolación lineal entre dos valores según la compacidad, con extremos en V/A = v_min y V/A = v_max
+fn interpolate_compacity(compacity: f32, (v_min, a): (f32, f32), (v_max, b): (f32, f32)) -> f32 {
+    let t = ((compacity - v_min) / (v_max - v_min)).clamp(0.0, 1.0);
+    a + t * (b - a)
+}
+
+/// Transmitancia térmica global límite, K_lim (W/m2K)
+///
+/// Depende del uso (residencial privado u otros usos), de si es edificio nuevo o ampliación
+/// (o, en otro caso, cambio de uso o reforma), de la zona climática de invierno y de la
+/// compacidad (V/A), interpolando linealmente entre V/A <= 1 y V/A >= 4.
+/// En usos distintos del residencial privado los cambios de uso tienen el mismo valor límite que
+/// los edificios nuevos (is_new_building = true) y el resto de casos es el de reformas.
+/// Tablas 3.1.1.b y 3.1.1.c - HE1
+pub fn k_lim(
+    climate: &ClimateZone,
+    is_dwelling: bool,
+    is_new_building: bool,
+    compacity: f32,
+) -> f32 {
+    #[rustfmt::skip]
+    let (v1, v4): ([f32; 6], [f32; 6]) = match (is_dwelling, is_new_building) {
+        //        α     A     B     C     D     E
+        (true, true) => (
+            [0.67, 0.60, 0.58, 0.53, 0.48, 0.43],
+            [0.86, 0.80, 0.77, 0.72, 0.67, 0.62],
+        ),
+        (true, false) => (
+            [1.00, 0.87, 0.83, 0.73, 0.63, 0.54],
+            [1.07, 0.94, 0.87, 0.81, 0.75, 0.70],
+        ),
+        (false, true) => (
+            [0.96, 0.81, 0.76, 0.65, 0.54, 0.43],
+            [1.12, 0.98, 0.92, 0.82, 0.70, 0.59],
+        ),
+        (false, false) => (
+            [1.17, 0.98, 0.91, 0.77, 0.64, 0.53],
+            [1.28, 1.07, 0.99, 0.86, 0.72, 0.58],
+        ),
+    };
+    let i = winter_index(climate);
+    interpolate_compacity(compacity, (1.0, v1[i]), (4.0, v4[i]))
+}
+
+/// Parámetro de control solar límite, q_sol;jul,lim (kWh/m2·mes)
+/// Tabla 3.1.2 - HE1
+pub fn q_soljul_lim(is_dwelling: bool) -> f32 {
+    if is_dwelling {
+        2.0
+    } else {
+        4.0
+    }
+}
+
+/// Relación de cambio de aire con una diferencia de presión de 50 Pa límite, n_50,lim (1/h)
+///
+/// Solo es de aplicación a edificios nuevos de uso residencial privado con V > 120 m3,
+/// interpolando linealmente entre V/A <= 2 y V/A >= 4.
+/// Tabla 3.1.3.a - HE1
+pub fn n50_lim(is_dwelling: bool, is_new_building: bool, compacity: f32) -> Option<f32> {
+    if is_dwelling && is_new_building {
+        Some(interpolate_compacity(compacity, (2.0, 6.0), (4.0, 3.0)))
+    } else {
+        None
+    }
+}
+
+impl Model {
+    /// Tipo de valor límite de transmitancia aplicable a un opaco de la envolvente térmica
+    ///
+    /// Devuelve None para los opacos que no pertenecen a la envolvente térmica (opacos de espacios
+    /// exteriores a la envolvente o particiones entre espacios interiores a ella)
+    pub fn u_lim_kind(&self, wall: &Wall) -> Option<ULimKind> {
+        self.u_lim_kind_with(wall, &self.index())
+    }
+
+    /// u_lim_kind usando los índices de los elementos del modelo (p.e. los de UValues::index)
+    pub fn u_lim_kind_with(&self, wall: &Wall, index: &ModelIndex) -> Option<ULimKind> {
+        use BoundaryType::*;
+        let space = index.get_wallspace(wall)?;
+        if !space.inside_tenv {
+            return None;
+        }
+        match wall.bounds {
+            EXTERIOR if Tilt::from(wall) == Tilt::TOP => Some(ULimKind::UC),
+            EXTERIOR => Some(ULimKind::UM),
+            GROUND => Some(ULimKind::UT),
+            ADIABATIC => Some(ULimKind::UMD),
+            INTERIOR => {
+                let nextto_inside = wall
+                    .nextto
+                    .as_ref()
+                    .and_then(|id| index.get_space(id))
+                    .map(|s| s.inside_tenv)
+                    .unwrap_or(false);
+                if nextto_inside {
+                    None
+                } else {
+                    Some(ULimKind::UT)
+                }
+            }
+        }
+    }
+
+    /// Transmitancia térmica global límite del edificio, K_lim (W/m2K)
+    pub fn K_lim(&self) -> f32 {
+        k_lim(
+            &self.meta.climate,
+            self.meta.is_dwelling,
+            self.meta.is_new_building,
+            self.compacity(),
+        )
+    }
+
+    /// Parámetro de control solar límite del edificio, q_sol;jul,lim (kWh/m2·mes)
+    pub fn q_soljul_lim(&self) -> f32 {
+        q_soljul_lim(self.meta.is_dwelling)
+    }
+
+    /// Relación de cambio de aire a 50 Pa límite del edificio, n_50,lim (1/h)
+    ///
+    /// No se aplica si el volumen de la envolvente térmica es inferior a 120 m3
+    pub fn n50_lim(&self) -> Option<f32> {
+        if self.vol_env_net() <= 120.0 {
+            return None;
+        }
+        n50_lim(
+            self.meta.is_dwelling,
+            self.meta.is_new_building,
+            self.compacity(),
+        )
+    }
+}
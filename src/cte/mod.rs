@@ -4,19 +4,56 @@
 
 //! Modelo del edificio que comprende los elementos de la envolvente térmica, espacios, construcciones y metadatos
 
+pub mod batch;
+pub mod btr;
+pub mod builder;
 pub mod climatedata;
 pub mod common;
+pub mod condensation;
+pub mod cooling_load;
+pub mod diff;
+pub mod dynamic;
+pub mod edit;
 pub(crate) mod from_ctehexml;
+pub mod ground;
+pub mod heat_load;
+pub mod heat_transfer;
+pub mod ids;
+pub mod index;
+pub mod limits;
 pub mod model_impl;
+pub mod optimizer;
+pub mod parametric;
+pub mod radiation;
+pub mod reference;
+pub mod shading;
+pub mod solar;
+pub mod uvalues;
 
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
+pub use batch::{batch_results_to_csv, BatchDefinition, BatchParameter};
+pub use builder::BuildingDefinition;
 pub use climatedata::*;
 pub use common::{
-    BoundaryType, ClimateZone, KDetail, N50HEDetail, Orientation, SpaceType, Tilt, Warning,
-    WarningLevel,
+    BatchResult, BoundaryType, BtrDetail, ClimateZone, CoolingLoadComponents, CoolingLoadDetail,
+    ElementDiff, ElementKind, FRsiCheck, FieldDiff, GlaserDetail, GlaserMonth,
+    GroundElementHeatFlow, GroundHeatFlowDetail, HeatLoadDetail, HeatLoadElement, HtrDetail,
+    HtrParts, HveDetail, HveParts, IdScheme, InsulationChange, InsulationPlan, KDetail, ModelDiff,
+    MonthlyClimate, N50HEDetail, Orientation, ProvinceClimateZones, RemovePolicy, ScenarioReport,
+    SolarGainsDetail, SpaceCoolingLoad, SpaceHeatLoad, SpaceType, SurfaceCondensationDetail, Tilt,
+    ValueDelta, Warning, WarningLevel, WindowUMethod,
 };
+pub use cooling_load::{CoolingDesignConditions, TertiaryGains};
+pub use heat_load::HeatingDesignConditions;
+pub use index::ModelIndex;
+pub use limits::ULimKind;
+pub use optimizer::{InsulationCandidate, InsulationOptions};
+pub use parametric::ConsChange;
+pub use shading::{ShadingDevice, ShadingPosition};
+pub use uvalues::UValues;
 
 // ---------- Estructura general de datos --------------
 
@@ -58,6 +95,7 @@ impl Model {
 
 /// Metadatos del edificio
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Meta {
     /// Nombre del proyecto
     pub name: String,
@@ -79,6 +117,15 @@ pub struct Meta {
     pub d_perim_insulation: f32,
     /// Resistencia térmica del aislamiento perimetral horizontal o vertical de la solera [m2K/W]
     pub rn_perim_insulation: f32,
+    /// Clase de higrometría de los espacios (1 a 5)
+    /// - 5: espacios con gran producción de humedad (lavanderías, piscinas...)
+    /// - 4: espacios con alta producción de humedad (cocinas industriales, restaurantes...)
+    /// - 3 o inferior: espacios sin gran producción de humedad (viviendas, oficinas...)
+    pub hygrometry: u8,
+    /// Espacio de nombres UUID del proyecto para la generación de id de los elementos
+    /// Se genera al importar el proyecto y no cambia al renombrarlo (ver Model::id_namespace)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_namespace: Option<Uuid>,
 }
 
 impl Default for Meta {
@@ -93,6 +140,8 @@ impl Default for Meta {
             n50_test_ach: None,
             d_perim_insulation: 0.0,
             rn_perim_insulation: 0.0,
+            hygrometry: 3,
+            id_namespace: None,
         }
     }
 }
@@ -130,6 +179,51 @@ pub struct Space {
     /// Incluye la parte del perímetro que separa el espacio del exterior
     /// y excluye que lo separa de otros espacios acondicionados.
     pub exposed_perimeter: Option<f32>,
+    /// Profundidad media del suelo del espacio respecto al terreno exterior (m)
+    /// Permite definir sótanos parcialmente enterrados (p.e. en parcelas en pendiente).
+    /// Si no se indica se usa la cota del espacio (z)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buried_depth: Option<f32>,
+    /// Cámara sanitaria bajo el suelo del espacio (UNE-EN ISO 13370:2017 9.4)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crawl_space: Option<CrawlSpace>,
+    /// Condiciones operacionales del espacio (p.e. Residencial, I_Media-16h-Acondicionado)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<String>,
+}
+
+/// Cámara sanitaria o espacio ventilado bajo un suelo (UNE-EN ISO 13370:2017 9.4)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrawlSpace {
+    /// Altura media de la cara superior del suelo sobre el terreno exterior, h (m)
+    pub height: f32,
+    /// Profundidad del suelo de la cámara bajo el terreno exterior, z (m)
+    pub depth: f32,
+    /// Área de aberturas de ventilación por unidad de perímetro de la cámara, ε (m2/m)
+    pub ventilation_openings: f32,
+    /// Transmitancia térmica de los muros de la cámara sobre el terreno, U_w (W/m2K)
+    pub u_walls: f32,
+    /// Resistencia térmica del aislamiento del suelo de la cámara, R_g (m2K/W)
+    pub r_floor: f32,
+    /// Velocidad media del viento a 10m de altura, v (m/s)
+    pub wind_speed: f32,
+    /// Factor de protección frente al viento, f_w (-)
+    pub wind_shielding: f32,
+}
+
+impl Default for CrawlSpace {
+    fn default() -> Self {
+        CrawlSpace {
+            height: 0.5,
+            depth: 0.0,
+            ventilation_openings: 0.003,
+            u_walls: 1.5,
+            r_floor: 0.0,
+            wind_speed: 4.0,
+            wind_shielding: 0.05,
+        }
+    }
 }
 
 /// Elemento opaco (muro, cubierta, suelo, partición)
@@ -194,10 +288,13 @@ pub struct ThermalBridge {
     pub l: f32,
     /// Transmitancia térmica lineal del puente térmico (W/mK)
     pub psi: f32,
+    /// Factor de temperatura de la superficie interior del puente térmico, fRsi (-)
+    pub frsi: Option<f32>,
 }
 
 /// Definición de construcción de elemento opaco
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WallCons {
     /// ID del espacio (en formato UUID)
     pub id: String,
@@ -212,6 +309,17 @@ pub struct WallCons {
     pub r_intrinsic: f32,
     /// Coeficiente de absortividad solar del elemento opaco (alpha) [0-1]
     pub absorptance: f32,
+    /// Capacidad térmica interior por unidad de área, κ1 (UNE-EN ISO 13786) [J/m2K]
+    pub kappa_1: f32,
+    /// Capacidad térmica exterior por unidad de área, κ2 (UNE-EN ISO 13786) [J/m2K]
+    pub kappa_2: f32,
+    /// Transmitancia térmica periódica, |Y12| (UNE-EN ISO 13786) [W/m2K]
+    #[serde(rename = "Y_12")]
+    pub y_12: f32,
+    /// Factor de amortiguamiento, f = |Y12| / U [-]
+    pub decrement_factor: f32,
+    /// Desfase de la transmitancia térmica periódica, Δt_f [h]
+    pub time_shift: f32,
 }
 
 /// Definición de construcción de hueco o lucernario
@@ -236,6 +344,9 @@ pub struct WindowCons {
     /// Permeabilidad al aire a 100 Pa [m3/hm2]
     #[serde(rename = "C_100")]
     pub infcoeff_100: f32,
+    /// Dispositivo de sombra móvil (UNE-EN 13363-1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shading: Option<ShadingDevice>,
 }
 
 /// Datos adicionales para comprobación de muros
@@ -259,6 +370,11 @@ pub struct ExtraData {
     pub u: f32,
     // U calculada con UNE-EN ISO 13789
     pub computed_u: f32,
+    // b_tr obtenido del archivo KyGananciasSolares.txt
+    pub btr: Option<f32>,
+    // b_tr calculado con UNE-EN ISO 13789
+    #[serde(default)]
+    pub computed_btr: f32,
 }
 
 /// Convierte de muro a enum Tilt
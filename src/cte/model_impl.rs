@@ -9,26 +9,103 @@
 #![allow(non_snake_case)]
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashSet},
     f32::consts::PI,
 };
 
 use log::{debug, info, warn};
 
 use super::{
-    BoundaryType, KDetail, Model, N50HEDetail, Orientation, Space, SpaceType, Tilt, Wall, WallCons,
-    Warning, WarningLevel, Window, WindowCons,
+    radiation::radiation_for_surface, BoundaryType, CrawlSpace, KDetail, Model, ModelIndex,
+    N50HEDetail, Space, SpaceType, Tilt, UValues, Wall, WallCons, Warning, WarningLevel, Window,
+    WindowCons,
 };
 use crate::utils::fround2;
 
 // Resistencias superficiales UNE-EN ISO 6946 [m2·K/W]
-const RSI_ASCENDENTE: f32 = 0.10;
-const RSI_HORIZONTAL: f32 = 0.13;
-const RSI_DESCENDENTE: f32 = 0.17;
-const RSE: f32 = 0.04;
+pub(crate) const RSI_ASCENDENTE: f32 = 0.10;
+pub(crate) const RSI_HORIZONTAL: f32 = 0.13;
+pub(crate) const RSI_DESCENDENTE: f32 = 0.17;
+pub(crate) const RSE: f32 = 0.04;
 // conductividad del terreno no helado, en [W/(m·K)]
-const LAMBDA_GND: f32 = 2.0;
-const LAMBDA_INS: f32 = 0.035;
+pub(crate) const LAMBDA_GND: f32 = 2.0;
+pub(crate) const LAMBDA_INS: f32 = 0.035;
+
+/// Profundidad del suelo de un espacio bajo el terreno exterior (m)
+/// Usa la profundidad media definida para el espacio o, en su defecto, su cota
+pub(crate) fn buried_depth(space: &Space) -> f32 {
+    match space.buried_depth {
+        Some(depth) => depth.max(0.0),
+        None if space.z < 0.0 => -space.z,
+        None => 0.0,
+    }
+}
+
+/// Transmitancia térmica de un suelo sobre cámara sanitaria ventilada
+/// UNE-EN ISO 13370:2017 9.4, ecuaciones (21) a (25)
+///
+/// - area, perimeter: superficie y perímetro expuesto de la cámara sanitaria (m2, m)
+/// - r_f: resistencia térmica intrínseca del suelo sobre la cámara (m2K/W)
+///
+/// Simplificaciones: espesor de muros perimetrales w = 0.3m. En cámaras con el suelo bajo la
+/// cota del terreno (z > 0) se añaden las pérdidas de la parte enterrada de sus muros (9.3.3)
+pub(crate) fn u_suspended_floor(crawl: &CrawlSpace, area: f32, perimeter: f32, r_f: f32) -> f32 {
+    const W: f32 = 0.3;
+    let U_f = 1.0 / (RSI_DESCENDENTE + r_f + RSI_DESCENDENTE);
+    if perimeter.abs() < 0.001 {
+        return U_f;
+    }
+    let B_1 = area / (0.5 * perimeter);
+    let z = crawl.depth.max(0.0);
+
+    // Suelo de la cámara sanitaria (22)
+    let d_g = W + LAMBDA_GND * (RSI_DESCENDENTE + crawl.r_floor + RSE);
+    let U_g =
+        2.0 * LAMBDA_GND / (PI * B_1 + d_g + 0.5 * z) * f32::ln(PI * B_1 / (d_g + 0.5 * z) + 1.0);
+
+    // Muros y ventilación de la cámara sanitaria (23)
+    let mut U_x = 2.0 * crawl.height * crawl.u_walls / B_1
+        + 1450.0 * crawl.ventilation_openings * crawl.wind_speed * crawl.wind_shielding / B_1;
+    if z > 0.0 {
+        // Parte enterrada de los muros de la cámara (24)
+        let R_w = (1.0 / crawl.u_walls - RSI_HORIZONTAL - RSE).max(0.0);
+        let d_w = LAMBDA_GND * (RSI_HORIZONTAL + R_w + RSE);
+        let U_bw =
+            (2.0 * LAMBDA_GND / (PI * z)) * (1.0 + 0.5 * d_g / (d_g + z)) * f32::ln(z / d_w + 1.0);
+        U_x += 2.0 * z * U_bw / B_1;
+    }
+
+    // (21)
+    1.0 / (1.0 / U_f + 1.0 / (U_g + U_x))
+}
+
+/// Resistencia térmica de un elemento interior entre un espacio acondicionado y otro no acondicionado,
+/// según la dirección del flujo de calor (UNE-EN ISO 13789 Tabla 8)
+///
+/// thiscondspace indica si el elemento pertenece al espacio acondicionado
+pub(crate) fn r_f_interior(position: Tilt, thiscondspace: bool, R_intrinsic: f32) -> f32 {
+    use Tilt::*;
+    match (position, thiscondspace) {
+        // Suelo de espacio acondicionado hacia no acondicionado inferior
+        // Techo de espacio no acondicionado hacia acondicionado inferior
+        (BOTTOM, true) | (TOP, false) => R_intrinsic + 2.0 * RSI_DESCENDENTE,
+        // Techo de espacio acondicionado hacia no acondicionado superior
+        // Suelo de espacio no acondicionado hacia acondicionado superior
+        (TOP, true) | (BOTTOM, false) => R_intrinsic + 2.0 * RSI_ASCENDENTE,
+        // Muro
+        (SIDE, _) => R_intrinsic + 2.0 * RSI_HORIZONTAL,
+    }
+}
+
+/// ¿Es un cerramiento de la envolvente térmica en contacto con el aire exterior o el terreno?
+/// Si el espacio no está definido se considera que no pertenece a la envolvente
+fn is_envelope_wall(index: &ModelIndex, wall: &Wall) -> bool {
+    [BoundaryType::EXTERIOR, BoundaryType::GROUND].contains(&wall.bounds)
+        && index
+            .get_space(&wall.space)
+            .map(|s| s.inside_tenv)
+            .unwrap_or(false)
+}
 
 impl Model {
     /// Localiza espacio
@@ -118,30 +195,51 @@ impl Model {
 
     /// Iterador de los cerramientos de la envolvente térmica en contacto con el aire o el terreno
     /// Se excluyen los opacos sin espacio definido
+    ///
+    /// Genera los índices del modelo en cada llamada, por lo que no debe usarse dentro de bucles
     pub fn walls_of_envelope(&self) -> impl Iterator<Item = &Wall> {
+        let index = self.index();
         self.walls
             .iter()
-            .filter(|w| [BoundaryType::EXTERIOR, BoundaryType::GROUND].contains(&w.bounds))
-            .filter(move |w| {
-                // Si el espacio no está definido se considera que no pertenece a la envolvente
-                self.get_space(&w.space)
-                    .map(|s| s.inside_tenv)
-                    .unwrap_or(false)
-            })
+            .filter(move |w| is_envelope_wall(&index, w))
+    }
+
+    /// Iterador de los cerramientos de la envolvente térmica, usando los índices de los elementos del modelo
+    pub(crate) fn walls_of_envelope_with<'a, 'b>(
+        &'a self,
+        index: &'b ModelIndex<'a>,
+    ) -> impl Iterator<Item = &'a Wall> + 'b
+    where
+        'a: 'b,
+    {
+        self.walls
+            .iter()
+            .filter(move |w| is_envelope_wall(index, w))
     }
 
     /// Iterador de los huecos de la envolvente térmica en contacto con el aire exterior
     /// Se excluyen los huecos sin espacio definido
+    ///
+    /// Genera los índices del modelo en cada llamada, por lo que no debe usarse dentro de bucles
     pub fn windows_of_envelope(&self) -> impl Iterator<Item = &Window> {
+        self.windows_of_envelope_with(&self.index())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Iterador de los huecos de la envolvente térmica, usando los índices de los elementos del modelo
+    pub(crate) fn windows_of_envelope_with<'a, 'b>(
+        &'a self,
+        index: &'b ModelIndex<'a>,
+    ) -> impl Iterator<Item = &'a Window> + 'b
+    where
+        'a: 'b,
+    {
         self.walls
             .iter()
             .filter(|w| w.bounds == BoundaryType::EXTERIOR)
-            .filter(move |w| {
-                self.get_space(&w.space)
-                    .map(|s| s.inside_tenv)
-                    .unwrap_or(false)
-            })
-            .flat_map(move |wall| self.windows.iter().filter(move |w| w.wall == wall.id))
+            .filter(move |w| is_envelope_wall(index, w))
+            .flat_map(move |wall| index.windows_of_wall(&wall.id))
     }
 
     /// Calcula la superficie útil de los espacios habitables de la envolvente térmica [m²]
@@ -180,12 +278,21 @@ impl Model {
     /// Computa el volumen de todos los espacios (habitables o no) de la envolvente y
     /// descuenta los volúmenes de forjados y cubiertas
     pub fn vol_env_net(&self) -> f32 {
+        self.vol_env_net_with(&self.index())
+    }
+
+    /// Volumen neto de los espacios de la envolvente, usando los índices de los elementos del modelo [m³]
+    pub(crate) fn vol_env_net_with(&self, index: &ModelIndex) -> f32 {
         let v_env: f32 = self
             .spaces
             .iter()
             .filter_map(|s| {
                 if s.inside_tenv {
-                    Some(s.area * (s.height - self.top_wall_thickness(&s.id)) * s.multiplier)
+                    Some(
+                        s.area
+                            * (s.height - self.top_wall_thickness_with(&s.id, index))
+                            * s.multiplier,
+                    )
                 } else {
                     None
                 }
@@ -197,12 +304,21 @@ impl Model {
     /// Computa el volumen de todos los espacios (solo habitables) de la envolvente y
     /// descuenta los volúmenes de forjados y cubiertas
     pub fn vol_env_inh_net(&self) -> f32 {
+        self.vol_env_inh_net_with(&self.index())
+    }
+
+    /// Volumen neto de los espacios habitables de la envolvente, usando los índices de los elementos del modelo [m³]
+    pub(crate) fn vol_env_inh_net_with(&self, index: &ModelIndex) -> f32 {
         let v_env: f32 = self
             .spaces
             .iter()
             .filter_map(|s| {
                 if s.inside_tenv && s.space_type != SpaceType::UNINHABITED {
-                    Some(s.area * (s.height - self.top_wall_thickness(&s.id)) * s.multiplier)
+                    Some(
+                        s.area
+                            * (s.height - self.top_wall_thickness_with(&s.id, index))
+                            * s.multiplier,
+                    )
                 } else {
                     None
                 }
@@ -219,11 +335,12 @@ impl Model {
     /// Para area expuesta => compacidad = 0.0
     pub fn compacity(&self) -> f32 {
         let vol: f32 = self.vol_env_gross();
+        let index = self.index();
         let area: f32 = self
-            .walls_of_envelope()
+            .walls_of_envelope_with(&index)
             .map(|w| {
-                let multiplier = self.get_wallspace(&w).map(|s| s.multiplier).unwrap_or(1.0);
-                let win_area: f32 = self.windows_of_wall(&w.id).map(|win| win.area).sum();
+                let multiplier = index.get_wallspace(w).map(|s| s.multiplier).unwrap_or(1.0);
+                let win_area: f32 = index.windows_of_wall(&w.id).map(|win| win.area).sum();
                 (w.area + win_area) * multiplier
             })
             .sum();
@@ -253,10 +370,17 @@ impl Model {
 
     /// Devuelve valor de la relación de cambio de aire por defecto o, en su caso, de ensayo
     pub fn n50(&self) -> f32 {
-        if let Some(n50test) = self.meta.n50_test_ach {
-            n50test
-        } else {
-            self.n50_he2019().n50
+        match self.meta.n50_test_ach {
+            Some(n50test) => n50test,
+            None => self.n50_he2019().n50,
+        }
+    }
+
+    /// n50 de ensayo o por defecto, usando los índices de los elementos del modelo (1/h)
+    pub(crate) fn n50_with(&self, index: &ModelIndex) -> f32 {
+        match self.meta.n50_test_ach {
+            Some(n50test) => n50test,
+            None => self.n50_he2019_with(index).n50,
         }
     }
 
@@ -269,7 +393,12 @@ impl Model {
     /// - el volumen interior de la envolvente térmica ()
     /// Se ignoran los huecos sin construcción definida y los muros sin espacio definido
     pub fn n50_he2019(&self) -> N50HEDetail {
-        let vol: f32 = self.vol_env_net();
+        self.n50_he2019_with(&self.index())
+    }
+
+    /// n50 según DB-HE2019, usando los índices de los elementos del modelo (1/h)
+    pub(crate) fn n50_he2019_with(&self, index: &ModelIndex) -> N50HEDetail {
+        let vol: f32 = self.vol_env_net_with(index);
         if vol <= 0.01 {
             info!(
                 "n_50=0.00 1/h, Σ(A_o.C_o)=- m³/h, Σ(A_h.C_h)=- m³/h, vol={:.2} m³",
@@ -284,14 +413,15 @@ impl Model {
         };
         let c_o = self.C_o_he2019();
         let (walls_c_a, windows_c_a) = self
-            .walls_of_envelope()
+            .walls_of_envelope_with(index)
             .filter(|w| w.bounds == BoundaryType::EXTERIOR)
             .map(|w| {
-                let multiplier = self.get_wallspace(&w).map(|s| s.multiplier).unwrap_or(1.0);
-                let wall_ah_ch: f32 = self
+                let multiplier = index.get_wallspace(w).map(|s| s.multiplier).unwrap_or(1.0);
+                let wall_ah_ch: f32 = index
                     .windows_of_wall(&w.id)
                     .filter_map(|win| {
-                        self.get_wincons(&win)
+                        index
+                            .get_wincons(win)
                             .map(|wincons| Some(win.area * wincons.infcoeff_100))?
                     })
                     .sum();
@@ -316,19 +446,21 @@ impl Model {
     /// Calcula la permeabilidad de opacos a partir de un ensayo de puerta soplante
     /// Se ignoran los huecos sin construcción definida y los muros sin espacio definido
     pub fn wall_inf_100_from_n50(&self, n50: f32) -> f32 {
-        let vol: f32 = self.vol_env_net();
+        let index = self.index();
+        let vol: f32 = self.vol_env_net_with(&index);
         let (sum_wall_area, sum_axc_h): (f32, f32) = self
-            .walls_of_envelope()
+            .walls_of_envelope_with(&index)
             .filter(|w| w.bounds == BoundaryType::EXTERIOR)
             .map(|w| {
-                let axc_h: f32 = self
+                let axc_h: f32 = index
                     .windows_of_wall(&w.id)
                     .filter_map(|win| {
-                        self.get_wincons(&win)
+                        index
+                            .get_wincons(win)
                             .map(|wincons| Some(win.area * wincons.infcoeff_100))?
                     })
                     .sum();
-                let multiplier = self.get_wallspace(&w).map(|s| s.multiplier).unwrap_or(1.0);
+                let multiplier = index.get_wallspace(w).map(|s| s.multiplier).unwrap_or(1.0);
                 (w.area * multiplier, axc_h * multiplier)
             })
             .fold(
@@ -350,23 +482,30 @@ impl Model {
     /// Incluye los puentes térmicos
     /// Se ignoran los huecos y muros para los que no está definida su construcción, transmitancia o espacio
     pub fn K_he2019(&self) -> KDetail {
+        self.K_he2019_with(&self.u_values())
+    }
+
+    /// K_he2019 con las transmitancias térmicas ya calculadas (ver Model::u_values)
+    pub fn K_he2019_with(&self, u_values: &UValues) -> KDetail {
+        let index = &u_values.index;
         let (walls_a_u, walls_a, windows_a_u, windows_a): (f32, f32, f32, f32) = self
-            .walls_of_envelope()
+            .walls_of_envelope_with(index)
             .filter_map(|wall| {
-                let (win_w_a_u, win_w_a) = self
+                let (win_w_a_u, win_w_a) = index
                     .windows_of_wall(&wall.id)
                     .filter_map(|win_i| {
-                        self.get_wincons(&win_i)
+                        index
+                            .get_wincons(win_i)
                             .map(|wincons| Some((win_i.area * wincons.u, win_i.area)))?
                     })
                     .fold((0.0, 0.0), |(acc_a_u, acc_a), (win_i_a_u, win_i_a)| {
                         (acc_a_u + win_i_a_u, acc_a + win_i_a)
                     });
-                let multiplier = self
-                    .get_wallspace(&wall)
+                let multiplier = index
+                    .get_wallspace(wall)
                     .map(|s| s.multiplier)
                     .unwrap_or(1.0);
-                let wall_u = self.u_for_wall(&wall)?;
+                let wall_u = u_values.wall(wall)?;
                 Some((
                     wall_u * wall.area * multiplier,
                     wall.area * multiplier,
@@ -419,18 +558,24 @@ impl Model {
     }
 
     /// Calcula el parámetro de control solar (q_sol;jul) a partir de los datos de radiación total acumulada en julio
+    /// La radiación se obtiene para la inclinación y orientación de cada hueco
     /// Los huecos para los que no está definido su opaco o su construcción no se consideran en el cálculo
-    pub fn q_soljul(&self, totradjul: &HashMap<Orientation, f32>) -> f32 {
+    pub fn q_soljul(&self) -> f32 {
+        self.q_soljul_with(&self.u_values())
+    }
+
+    /// q_soljul con los índices de los elementos ya calculados (ver Model::u_values)
+    pub fn q_soljul_with(&self, u_values: &UValues) -> f32 {
+        let index = &u_values.index;
         let Q_soljul = self
-            .windows_of_envelope()
+            .windows_of_envelope_with(index)
             .filter_map(|w| {
-                let wall = self.get_windowwall(&w)?;
-                let wincons = self.get_wincons(&w)?;
-                let orientation = Orientation::from(wall);
-                let radjul = totradjul.get(&orientation).unwrap();
+                let wall = index.get_windowwall(w)?;
+                let wincons = index.get_wincons(w)?;
+                let radjul = radiation_for_surface(self.meta.climate, wall.tilt, wall.azimuth).tot[6];
                 debug!(
-                    "qsoljul de {}: A {:.2}, orient {}, ff {:.2}, gglshwi {:.2}, fshobst {:.2}, H_sol;jul {:.2}",
-                    w.name, w.area, orientation, wincons.ff, wincons.gglshwi, w.fshobst, radjul
+                    "qsoljul de {}: A {:.2}, tilt {:.1}, azimuth {:.1}, ff {:.2}, gglshwi {:.2}, fshobst {:.2}, H_sol;jul {:.2}",
+                    w.name, w.area, wall.tilt, wall.azimuth, wincons.ff, wincons.gglshwi, w.fshobst, radjul
                 );
                 Some(w.fshobst * wincons.gglshwi * (1.0 - wincons.ff) * w.area * radjul)
             })
@@ -454,7 +599,56 @@ impl Model {
     ///       las construcciones por defecto
     /// - los elementos adiabáticos se reportan con valor 0.0
     /// - los elementos mal definidos (muros sin construcción o sin espacio asignado) se reportan con valor 0.0
+    ///
+    /// Genera los índices del modelo en cada llamada, por lo que para obtener la U de varios
+    /// elementos del modelo debe usarse u_values()
     pub fn u_for_wall(&self, wall: &Wall) -> Option<f32> {
+        use BoundaryType::*;
+
+        let index = self.index();
+        // La U de las particiones con espacios no acondicionados usa la de los opacos de esos
+        // espacios al exterior o al terreno, que se calculan antes
+        let mut cache = BTreeMap::new();
+        if wall.bounds == INTERIOR {
+            let spaces = std::iter::once(wall.space.as_str()).chain(wall.nextto.as_deref());
+            for space in spaces {
+                for w in index
+                    .walls_of_space(space)
+                    .filter(|w| w.bounds == EXTERIOR || w.bounds == GROUND)
+                {
+                    if let Some(u) = self.u_for_wall_with(w, &cache, &index) {
+                        cache.insert(w.id.as_str(), u);
+                    }
+                }
+            }
+        }
+        self.u_for_wall_with(wall, &cache, &index)
+    }
+
+    /// Transmitancia térmica de un opaco, usando los valores ya calculados de otros opacos (cache)
+    /// y los índices de los elementos del modelo
+    pub(crate) fn u_for_wall_with(
+        &self,
+        wall: &Wall,
+        cache: &BTreeMap<&str, f32>,
+        index: &ModelIndex,
+    ) -> Option<f32> {
+        if let Some(u) = cache.get(wall.id.as_str()) {
+            return Some(*u);
+        }
+        let cons = index.get_wallcons(wall)?;
+        self.u_for_wall_with_r(wall, cons.r_intrinsic, cache, index)
+    }
+
+    /// Transmitancia térmica de un opaco con la resistencia intrínseca de construcción indicada
+    /// (m2K/W), usando los valores ya calculados de otros opacos (cache) y los índices del modelo
+    pub(crate) fn u_for_wall_with_r(
+        &self,
+        wall: &Wall,
+        R_intrinsic: f32,
+        cache: &BTreeMap<&str, f32>,
+        index: &ModelIndex,
+    ) -> Option<f32> {
         use {BoundaryType::*, SpaceType::*, Tilt::*};
 
         let position = Tilt::from(wall);
@@ -462,9 +656,6 @@ impl Model {
         let R_n_perim_ins = self.meta.rn_perim_insulation;
         let D_perim_ins = self.meta.d_perim_insulation;
 
-        let cons = self.get_wallcons(&wall)?;
-        let R_intrinsic = cons.r_intrinsic;
-
         match (bounds, position) {
             // Elementos adiabáticos -----------------------------
             (ADIABATIC, _) => {
@@ -502,7 +693,7 @@ impl Model {
                 // Dimensión característica del suelo (B'). Ver UNE-EN ISO 13370:2010 8.1
                 // Calculamos la dimensión característica del **espacio** en el que sitúa el suelo
                 // Si este espacio no define el perímetro, lo calculamos suponiendo una superficie cuadrada
-                let wspace = self.get_wallspace(&wall)?;
+                let wspace = index.get_wallspace(wall)?;
                 let gnd_A = wspace.area;
                 let gnd_P = wspace
                     .exposed_perimeter
@@ -519,7 +710,17 @@ impl Model {
 
                 let B_1 = gnd_A / (0.5 * gnd_P);
 
-                let z = if wspace.z < 0.0 { -wspace.z } else { 0.0 };
+                // Suelos sobre cámara sanitaria: UNE-EN ISO 13370:2017 9.4
+                if let Some(crawl) = wspace.crawl_space.as_ref() {
+                    let U = u_suspended_floor(crawl, gnd_A, gnd_P, R_intrinsic);
+                    debug!(
+                        "{} (suelo sobre cámara sanitaria) U={:.2} (A={:.2}, P={:.2}, R_f={:.3})",
+                        wall.name, U, gnd_A, gnd_P, R_intrinsic
+                    );
+                    return Some(U);
+                }
+
+                let z = buried_depth(wspace);
                 const W: f32 = 0.3; // Simplificación: espesor supuesto de los muros perimetrales
                 let d_t = W + LAMBDA_GND * (RSI_DESCENDENTE + R_intrinsic + RSE);
 
@@ -561,8 +762,8 @@ impl Model {
             (GROUND, SIDE) => {
                 // 2. Muros enterrados UNE-EN ISO 13370:2010 9.3.3
                 let U_w = 1.0 / (RSI_HORIZONTAL + R_intrinsic + RSE);
-                let space = self.get_wallspace(&wall)?;
-                let z = if space.z < 0.0 { -space.z } else { 0.0 };
+                let space = index.get_wallspace(wall)?;
+                let z = buried_depth(space);
                 // Muros que realmente no son enterrados
                 if z.abs() < 0.01 {
                     warn!(
@@ -575,13 +776,14 @@ impl Model {
                 // Dimensión característica del suelo del sótano.
                 // Suponemos espesor de muros de sótano = 0.30m para cálculo de soleras
                 // Usamos el promedio de los suelos del espacio
-                let mut d_t = self
+                let mut d_t = index
                     .walls_of_space(&space.id)
                     .filter(|w| Tilt::from(*w) == BOTTOM)
                     .zip(1..)
                     .fold(0.0, |mean, (w, i)| {
                         // Si no está definida la construcción no participa de la envolvente
-                        self.get_wallcons(&w)
+                        index
+                            .get_wallcons(w)
                             .map(|wallcons| {
                                 (W + LAMBDA_GND * (RSI_DESCENDENTE + wallcons.r_intrinsic + RSE)
                                     + mean * (i - 1) as f32)
@@ -608,7 +810,7 @@ impl Model {
                 };
 
                 // Altura neta
-                let height_net = space.height - self.top_wall_thickness(&space.id);
+                let height_net = space.height - self.top_wall_thickness_with(&space.id, index);
 
                 // Altura sobre el terreno (muro no enterrado)
                 let h = if height_net > z { height_net - z } else { 0.0 };
@@ -642,7 +844,7 @@ impl Model {
                 // Dos casos:
                 // - Suelos en contacto con sótanos no acondicionados / no habitables en contacto con el terreno - ISO 13370:2010 (9.4)
                 // - Elementos en contacto con espacios no acondicionados / no habitables - UNE-EN ISO 6946:2007 (5.4.3)
-                let space = self.get_wallspace(&wall)?;
+                let space = index.get_wallspace(wall)?;
                 let nextto = match wall.nextto.as_ref() {
                     Some(s) => s,
                     _ => {
@@ -654,7 +856,7 @@ impl Model {
                     }
                 };
 
-                let nextspace = match self.get_space(nextto.as_str()) {
+                let nextspace = match index.get_space(nextto.as_str()) {
                     Some(s) => s,
                     _ => {
                         warn!(
@@ -695,57 +897,28 @@ impl Model {
                     };
 
                     // Resistencia del elemento teniendo en cuenta el flujo de calor (UNE-EN ISO 13789 Tabla 8)
-                    let R_f = match (position, thiscondspace) {
-                        // Suelo de espacio acondicionado hacia no acondicionado inferior
-                        // Techo de espacio no acondicionado hacia acondicionado inferior
-                        (BOTTOM, true) | (TOP, false) => R_intrinsic + 2.0 * RSI_DESCENDENTE,
-                        // Techo de espacio acondicionado hacia no acondicionado superior
-                        // Suelo de espacio no acondicionado hacia acondicionado superior
-                        (TOP, true) | (BOTTOM, false) => R_intrinsic + 2.0 * RSI_ASCENDENTE,
-                        // Muro
-                        (SIDE, _) => R_intrinsic + 2.0 * RSI_HORIZONTAL,
-                    };
-
-                    // Intercambio de aire en el espacio no acondicionado (¿o podría ser el actual si es el no acondicionado?)
-                    let uncondspace_v = (uncondspace.height
-                        - self.top_wall_thickness(&uncondspace.id))
-                        * uncondspace.area;
-                    let n_ven = match uncondspace.n_v {
-                        Some(n_v) => n_v,
-                        _ => match self.meta.global_ventilation_l_s {
-                            Some(global_ventilation) => {
-                                3.6 * global_ventilation / self.vol_env_inh_net()
-                            }
-                            _ => {
-                                // Espacio mal definido (ni tiene n_v ni hay definición global de ventilación)
-                                warn!("Definición global (l/s) no definida para espacio no acondicionado sin n_v {} ({})", uncondspace.id, uncondspace.name);
-                                0.0
-                            }
-                        },
-                    };
+                    let R_f = r_f_interior(position, thiscondspace, R_intrinsic);
+
+                    // CASO: suelo sobre cámara sanitaria definida como espacio - UNE-EN ISO 13370:2017 (9.4)
+                    if let (BOTTOM, true, Some(crawl)) =
+                        (position, thiscondspace, uncondspace.crawl_space.as_ref())
+                    {
+                        let A = uncondspace.area;
+                        let P = uncondspace
+                            .exposed_perimeter
+                            .unwrap_or_else(|| 4.0 * f32::sqrt(A));
+                        let U = u_suspended_floor(crawl, A, P, R_intrinsic);
+                        debug!(
+                            "{} ({} sobre cámara sanitaria) U={:.2} (A={:.2}, P={:.2}, R_f={:.3})",
+                            wall.name, posname, U, A, P, R_intrinsic
+                        );
+                        return Some(U);
+                    }
 
                     // CASO: interior en contacto con sótano no calefactado - ISO 13370:2010 (9.4)
                     // CASO: interior en contacto con otro espacio no habitable / no acondicionado - UNE-EN ISO 6946:2007 (5.4.3)
-                    // Calculamos el A.U de los elementos del espacio que dan al exterior o al terreno (excluye interiores))
-                    // Como hemos asignado U_bw y U_bf a los muros y suelos en contacto con el terreno, ya se tiene en cuenta
-                    // la parte enterrada correctamente (fracción enterrada y superficie expuesta, ya que no se consideran los que dan a interiores)
-                    let UA_e_k = self
-                        .walls_of_space(&uncondspace.id)
-                        .filter(|wall| wall.bounds == GROUND || wall.bounds == EXTERIOR)
-                        .filter_map(|wall| {
-                            // A·U de muros (y suelos) + A.U de sus huecos
-                            let wall_u = self.u_for_wall(wall)?;
-                            let win_axu = self
-                                .windows_of_wall(&wall.id)
-                                .filter_map(|win| {
-                                    self.get_wincons(&win)
-                                        // Si no está definida la construcción no participa de la envolvente
-                                        .map(|wincons| Some(win.area * wincons.u))?
-                                })
-                                .sum::<f32>();
-                            Some(wall.area * wall_u + win_axu)
-                        })
-                        .sum::<f32>();
+                    let (UA_e_k, n_ven, uncondspace_v) =
+                        self.h_ue_of_space(uncondspace, cache, index);
                     // 1/U = 1/U_f + A_i / (sum_k(A_e_k·U_e_k) + 0.33·n·V) (17)
                     // En la fórmula anterior, para espacios no acondicionados, se indica que se excluyen suelos, pero no entiendo bien por qué.
                     // Esta fórmula, cuando los A_e_k y U_e_k incluyen los muros y suelos con el terreno U_bw y U_bf, con la parte proporcional de
@@ -765,32 +938,78 @@ impl Model {
         }
     }
 
+    /// Datos para el cálculo del coeficiente de transmisión de calor de un espacio no acondicionado
+    /// al exterior, H_ue = Σ A·U + 0.33·n·V (UNE-EN ISO 13789:2017 6.4)
+    ///
+    /// Devuelve (Σ A·U de opacos y huecos al exterior o al terreno, n (ren/h), V (m3))
+    pub(crate) fn h_ue_of_space(
+        &self,
+        uncondspace: &Space,
+        cache: &BTreeMap<&str, f32>,
+        index: &ModelIndex,
+    ) -> (f32, f32, f32) {
+        use BoundaryType::*;
+        // Intercambio de aire en el espacio no acondicionado (¿o podría ser el actual si es el no acondicionado?)
+        let uncondspace_v = (uncondspace.height
+            - self.top_wall_thickness_with(&uncondspace.id, index))
+            * uncondspace.area;
+        let n_ven = match uncondspace.n_v {
+            Some(n_v) => n_v,
+            _ => match self.meta.global_ventilation_l_s {
+                Some(global_ventilation) => {
+                    3.6 * global_ventilation / self.vol_env_inh_net_with(index)
+                }
+                _ => {
+                    // Espacio mal definido (ni tiene n_v ni hay definición global de ventilación)
+                    warn!("Definición global (l/s) no definida para espacio no acondicionado sin n_v {} ({})", uncondspace.id, uncondspace.name);
+                    0.0
+                }
+            },
+        };
+
+        // Calculamos el A.U de los elementos del espacio que dan al exterior o al terreno (excluye interiores))
+        // Como hemos asignado U_bw y U_bf a los muros y suelos en contacto con el terreno, ya se tiene en cuenta
+        // la parte enterrada correctamente (fracción enterrada y superficie expuesta, ya que no se consideran los que dan a interiores)
+        let UA_e_k = index
+            .walls_of_space(&uncondspace.id)
+            .filter(|wall| wall.bounds == GROUND || wall.bounds == EXTERIOR)
+            .filter_map(|wall| {
+                // A·U de muros (y suelos) + A.U de sus huecos
+                let wall_u = self.u_for_wall_with(wall, cache, index)?;
+                let win_axu = index
+                    .windows_of_wall(&wall.id)
+                    .filter_map(|win| {
+                        index
+                            .get_wincons(win)
+                            // Si no está definida la construcción no participa de la envolvente
+                            .map(|wincons| Some(win.area * wincons.u))?
+                    })
+                    .sum::<f32>();
+                Some(wall.area * wall_u + win_axu)
+            })
+            .sum::<f32>();
+        (UA_e_k, n_ven, uncondspace_v)
+    }
+
     /// Elemento opaco de techo de un espacio
-    fn top_wall_of_space<'a>(&'a self, spaceid: &'a str) -> Option<&'a Wall> {
-        self.walls.iter().find(move |w| {
+    fn top_wall_of_space<'a>(index: &ModelIndex<'a>, spaceid: &str) -> Option<&'a Wall> {
+        index.walls_of_space(spaceid).find(|w| {
             match w.tilt.into() {
                 // Muros exteriores o cubiertas sobre el espacio
-                Tilt::TOP => &w.space == &spaceid,
+                Tilt::TOP => w.space == spaceid,
                 // Es un cerramiento interior sobre este espacio
-                Tilt::BOTTOM => w.nextto.as_ref().map(|s| s == &spaceid).unwrap_or(false),
+                Tilt::BOTTOM => w.nextto.as_ref().map(|s| s == spaceid).unwrap_or(false),
                 _ => false,
             }
         })
     }
 
-    /// Grosor de un elemento opaco
-    fn wall_thickness(&self, wallid: &str) -> f32 {
-        self.get_wall(wallid)
-            .and_then(|w| self.get_wallcons(&w).map(|c| c.thickness))
-            .unwrap_or(0.0)
-    }
-
-    /// Grosor del forjado superior de un espacio
+    /// Grosor del forjado superior de un espacio, usando los índices de los elementos del modelo
     /// TODO: la altura neta debería calcularse promediando los grosores de todos los muros que cierren el espacio,
     /// TODO: estos podrían ser más de uno pero este cálculo ahora se hace con el primero que se localiza
-    fn top_wall_thickness(&self, spaceid: &str) -> f32 {
-        self.top_wall_of_space(&spaceid)
-            .map(|w| self.wall_thickness(&w.id))
+    pub(crate) fn top_wall_thickness_with(&self, spaceid: &str, index: &ModelIndex) -> f32 {
+        Self::top_wall_of_space(index, spaceid)
+            .and_then(|w| index.get_wallcons(w).map(|c| c.thickness))
             .unwrap_or(0.0)
     }
 
@@ -0,0 +1,437 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Optimización del espesor de aislamiento para cumplir los valores límite de K y U
+//!
+//! Se busca el menor espesor de aislamiento añadido a cada construcción de opaco de la envolvente,
+//! eligiendo para cada una el material candidato:
+//! 1. Para cumplir la transmitancia límite (Tabla 3.1.1.a - HE1) de todos los opacos que usan la construcción,
+//!    con el material que lo consigue con menor coste (o volumen de aislamiento, si no hay costes)
+//! 2. Para alcanzar la K objetivo, aplicando de forma voraz el cambio (construcción, material) que consigue
+//!    mayor reducción de K por unidad de coste (o volumen). Cada cambio aumenta la resistencia añadida a
+//!    la construcción, con un incremento de espesor del mismo material o con el menor espesor de otro
+//! 3. Reduciendo, tras alcanzar la K objetivo, el aislamiento que deja de ser necesario, con el cambio
+//!    (construcción, material) de mayor ahorro que mantiene la K objetivo y las transmitancias límite
+//!
+//! La búsqueda es heurística: no garantiza el mínimo coste o espesor global, aunque la pasada de
+//! reducción elimina el exceso de aislamiento de la búsqueda voraz.
+//! La K se evalúa con Model::K_he2019 sobre el modelo modificado, de modo que el resultado
+//! es válido para el conjunto del edificio.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, format_err, Error};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    limits::{u_lim, ULimKind},
+    ConsChange, InsulationChange, InsulationPlan, Model,
+};
+use crate::{bdl, utils::fround3};
+
+/// Material aislante candidato
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsulationCandidate {
+    /// Nombre del material en la base de datos (p.e. del catálogo de HULC)
+    pub material: String,
+    /// Coste por unidad de volumen (€/m3)
+    #[serde(default)]
+    pub cost: Option<f32>,
+}
+
+/// Opciones de la optimización del aislamiento
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InsulationOptions {
+    /// Materiales aislantes candidatos
+    pub candidates: Vec<InsulationCandidate>,
+    /// Transmitancia térmica global objetivo (W/m2K)
+    pub k_target: Option<f32>,
+    /// ¿Cumplir las transmitancias límite de los elementos?
+    pub u_limits: bool,
+    /// Incremento de espesor de la búsqueda (m)
+    pub step: f32,
+    /// Espesor máximo añadido a cada construcción (m)
+    pub max_thickness: f32,
+}
+
+impl Default for InsulationOptions {
+    fn default() -> Self {
+        InsulationOptions {
+            candidates: Vec::new(),
+            k_target: None,
+            u_limits: true,
+            step: 0.01,
+            max_thickness: 0.30,
+        }
+    }
+}
+
+/// Material aislante candidato, con su conductividad
+struct Candidate {
+    /// Nombre del material
+    material: String,
+    /// Conductividad térmica (W/mK)
+    lambda: f32,
+    /// Coste por unidad de volumen (€/m3)
+    cost: Option<f32>,
+}
+
+/// Construcción en estudio
+#[derive(Debug, Clone, Copy)]
+struct ConsState {
+    /// Resistencia intrínseca inicial (m2K/W)
+    r_intrinsic: f32,
+    /// Espesor inicial (m)
+    thickness: f32,
+    /// Superficie de los opacos que la usan, con multiplicadores (m2)
+    area: f32,
+    /// Material añadido (posición en la lista de candidatos)
+    material: usize,
+    /// Número de incrementos de espesor añadidos
+    steps: usize,
+}
+
+impl ConsState {
+    /// Estado con otro material y número de incrementos de espesor
+    fn with(&self, material: usize, steps: usize) -> Self {
+        ConsState {
+            material,
+            steps,
+            ..*self
+        }
+    }
+
+    /// Resistencia térmica añadida (m2K/W)
+    fn r_added(&self, candidates: &[Candidate], step: f32) -> f32 {
+        self.steps as f32 * step / candidates[self.material].lambda
+    }
+
+    /// Coste del aislamiento añadido o, sin costes, su volumen
+    fn cost(&self, candidates: &[Candidate], step: f32) -> f32 {
+        self.steps as f32 * step * self.area * candidates[self.material].cost.unwrap_or(1.0)
+    }
+}
+
+impl Model {
+    /// Busca el menor espesor de aislamiento añadido por construcción para cumplir los objetivos de U y K
+    ///
+    /// db es la base de datos BDL con los materiales candidatos (p.e. del catálogo de HULC)
+    pub fn optimize_insulation(
+        &self,
+        db: &bdl::DB,
+        options: &InsulationOptions,
+    ) -> Result<InsulationPlan, Error> {
+        let candidates = candidates(db, &options.candidates)?;
+        let step = options.step.max(0.001);
+        let max_steps = (options.max_thickness / step + 0.0001).floor() as usize;
+
+        // Construcciones de la envolvente y tipo de límite de sus opacos
+        let u_values = self.u_values();
+        let index = &u_values.index;
+        let mut kinds: BTreeMap<String, Vec<(String, ULimKind)>> = BTreeMap::new();
+        let mut state: BTreeMap<String, ConsState> = BTreeMap::new();
+        for wall in &self.walls {
+            let cons = match index.get_wallcons(wall) {
+                Some(cons) => cons,
+                None => continue,
+            };
+            // Superficie de todos los opacos que usan la construcción
+            let st = state.entry(cons.name.clone()).or_insert(ConsState {
+                r_intrinsic: cons.r_intrinsic,
+                thickness: cons.thickness,
+                area: 0.0,
+                material: 0,
+                steps: 0,
+            });
+            st.area += wall.area
+                * index
+                    .get_wallspace(wall)
+                    .map(|s| s.multiplier)
+                    .unwrap_or(1.0);
+            match self.u_lim_kind_with(wall, index) {
+                Some(ULimKind::UMD) | None => continue,
+                Some(kind) => kinds
+                    .entry(cons.name.clone())
+                    .or_default()
+                    .push((wall.id.clone(), kind)),
+            }
+        }
+        // Solo se modifican las construcciones de la envolvente
+        state.retain(|name, _| kinds.contains_key(name));
+
+        let mut model = self.clone();
+        let k_before = self.K_he2019_with(&u_values).K;
+        let mut feasible = true;
+
+        // 1. Transmitancias límite, con el material de menor coste (o volumen) que las cumple
+        if options.u_limits {
+            let climate = self.meta.climate;
+            for (name, walls) in &kinds {
+                let st0 = state[name];
+                let mut best: Option<ConsState> = None;
+                for material in 0..candidates.len() {
+                    for steps in 0..=max_steps {
+                        let trial = st0.with(material, steps);
+                        set_insulation(&mut model, name, &trial, &candidates, step);
+                        let u_values = model.u_values();
+                        let ok = walls.iter().all(|(id, kind)| {
+                            let wall = u_values.index.get_wall(id).unwrap();
+                            u_values
+                                .wall(wall)
+                                .map(|u| u <= u_lim(&climate, *kind) + 0.001)
+                                .unwrap_or(true)
+                        });
+                        if ok {
+                            if best
+                                .map(|b| trial.cost(&candidates, step) < b.cost(&candidates, step))
+                                .unwrap_or(true)
+                            {
+                                best = Some(trial);
+                            }
+                            break;
+                        }
+                    }
+                }
+                let st = state.get_mut(name).unwrap();
+                match best {
+                    Some(best) => *st = best,
+                    None => {
+                        // Se deja el mayor espesor del material de menor conductividad
+                        feasible = false;
+                        let material = (0..candidates.len())
+                            .min_by(|&a, &b| {
+                                candidates[a]
+                                    .lambda
+                                    .partial_cmp(&candidates[b].lambda)
+                                    .unwrap()
+                            })
+                            .unwrap();
+                        *st = st0.with(material, max_steps);
+                    }
+                }
+                set_insulation(&mut model, name, st, &candidates, step);
+            }
+        }
+
+        // Resistencia añadida necesaria para cumplir las transmitancias límite
+        let r_min: BTreeMap<String, f32> = state
+            .iter()
+            .map(|(name, st)| (name.clone(), st.r_added(&candidates, step)))
+            .collect();
+
+        // 2. Transmitancia térmica global objetivo
+        if let Some(k_target) = options.k_target {
+            let mut k = model.K_he2019().K;
+            while k > k_target {
+                let mut best: Option<(String, ConsState, f32, f32)> = None;
+                for (name, st) in &state {
+                    if st.area <= 0.0 {
+                        continue;
+                    }
+                    let r_cur = st.r_added(&candidates, step);
+                    for material in 0..candidates.len() {
+                        // Un incremento más del mismo material o el menor espesor de otro que aumente la resistencia
+                        let steps = if material == st.material && st.steps > 0 {
+                            st.steps + 1
+                        } else {
+                            (r_cur * candidates[material].lambda / step + 0.0001).floor() as usize
+                                + 1
+                        };
+                        if steps > max_steps {
+                            continue;
+                        }
+                        let trial = st.with(material, steps);
+                        set_insulation(&mut model, name, &trial, &candidates, step);
+                        let k_trial = model.K_he2019().K;
+                        set_insulation(&mut model, name, st, &candidates, step);
+                        let delta_cost = trial.cost(&candidates, step) - st.cost(&candidates, step);
+                        let score = if delta_cost > 0.0 {
+                            (k - k_trial) / delta_cost
+                        } else {
+                            f32::INFINITY
+                        };
+                        if k_trial < k && best.as_ref().map(|b| score > b.3).unwrap_or(true) {
+                            best = Some((name.clone(), trial, k_trial, score));
+                        }
+                    }
+                }
+                match best {
+                    Some((name, trial, k_new, _)) => {
+                        set_insulation(&mut model, &name, &trial, &candidates, step);
+                        debug!(
+                            "Aislamiento {:.3} m de {} en {}: K={:.3}",
+                            trial.steps as f32 * step,
+                            candidates[trial.material].material,
+                            name,
+                            k_new
+                        );
+                        state.insert(name, trial);
+                        k = k_new;
+                    }
+                    None => {
+                        feasible = false;
+                        break;
+                    }
+                }
+            }
+
+            // 3. Reducción del aislamiento innecesario
+            if feasible {
+                loop {
+                    let mut best: Option<(String, ConsState, f32, f32)> = None;
+                    for (name, st) in &state {
+                        let r_cur = st.r_added(&candidates, step);
+                        for material in 0..candidates.len() {
+                            // Un incremento menos del mismo material o el mayor espesor de otro que reduzca la resistencia
+                            let steps = if material == st.material {
+                                match st.steps.checked_sub(1) {
+                                    Some(steps) => steps,
+                                    None => continue,
+                                }
+                            } else {
+                                let r_steps = r_cur * candidates[material].lambda / step;
+                                match (r_steps - 0.0001).ceil() as usize {
+                                    0 => continue,
+                                    steps => (steps - 1).min(max_steps),
+                                }
+                            };
+                            let trial = st.with(material, steps);
+                            if trial.r_added(&candidates, step) < r_min[name] - 0.0001 {
+                                continue;
+                            }
+                            let saving = st.cost(&candidates, step) - trial.cost(&candidates, step);
+                            if saving <= 0.0
+                                || best.as_ref().map(|b| saving <= b.3).unwrap_or(false)
+                            {
+                                continue;
+                            }
+                            set_insulation(&mut model, name, &trial, &candidates, step);
+                            let k_trial = model.K_he2019().K;
+                            set_insulation(&mut model, name, st, &candidates, step);
+                            if k_trial <= k_target {
+                                best = Some((name.clone(), trial, k_trial, saving));
+                            }
+                        }
+                    }
+                    match best {
+                        Some((name, trial, k_new, _)) => {
+                            set_insulation(&mut model, &name, &trial, &candidates, step);
+                            debug!(
+                                "Aislamiento {:.3} m de {} en {}: K={:.3}",
+                                trial.steps as f32 * step,
+                                candidates[trial.material].material,
+                                name,
+                                k_new
+                            );
+                            state.insert(name, trial);
+                        }
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        let changes = state
+            .iter()
+            .filter(|(_, st)| st.steps > 0)
+            .map(|(name, st)| {
+                let candidate = &candidates[st.material];
+                let added = st.steps as f32 * step;
+                InsulationChange {
+                    wallcons: name.clone(),
+                    material: candidate.material.clone(),
+                    thickness: fround3(added),
+                    area: st.area,
+                    r_added: st.r_added(&candidates, step),
+                    cost: candidate.cost.map(|c| c * added * st.area),
+                }
+            })
+            .collect::<Vec<_>>();
+        let k_after = model.K_he2019().K;
+        let total_cost = candidates
+            .iter()
+            .all(|c| c.cost.is_some())
+            .then(|| changes.iter().filter_map(|c| c.cost).sum());
+        info!(
+            "Optimización del aislamiento: K {:.3} -> {:.3} W/m²K, {} construcciones modificadas",
+            k_before,
+            k_after,
+            changes.len()
+        );
+        Ok(InsulationPlan {
+            k_before,
+            k_after,
+            k_target: options.k_target,
+            feasible,
+            total_cost,
+            changes,
+        })
+    }
+}
+
+impl InsulationPlan {
+    /// Modificaciones de construcciones equivalentes, para su uso con Model::apply_cons_changes
+    ///
+    /// El aislamiento se añade en la cara exterior de las composiciones
+    pub fn cons_changes(&self) -> Vec<ConsChange> {
+        self.changes
+            .iter()
+            .map(|c| ConsChange::AddLayer {
+                wallcons: c.wallcons.clone(),
+                material: c.material.clone(),
+                thickness: c.thickness,
+                position: None,
+            })
+            .collect()
+    }
+}
+
+/// Materiales candidatos y su conductividad
+///
+/// Los costes deben indicarse para todos los candidatos o para ninguno
+fn candidates(db: &bdl::DB, candidates: &[InsulationCandidate]) -> Result<Vec<Candidate>, Error> {
+    let with_cost = candidates.iter().filter(|c| c.cost.is_some()).count();
+    if with_cost != 0 && with_cost != candidates.len() {
+        bail!("Los costes deben indicarse para todos los materiales candidatos o para ninguno");
+    }
+    if candidates.is_empty() {
+        bail!("No se ha indicado ningún material aislante candidato");
+    }
+    candidates
+        .iter()
+        .map(|c| {
+            let mat = db
+                .materials
+                .get(&c.material)
+                .ok_or_else(|| format_err!("Material no encontrado: {}", c.material))?;
+            match mat.properties {
+                Some(props) if props.conductivity > 0.0 => Ok(Candidate {
+                    material: c.material.clone(),
+                    lambda: props.conductivity,
+                    cost: c.cost,
+                }),
+                _ => bail!(
+                    "El material {} no está definido por su conductividad",
+                    c.material
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Actualiza la construcción del modelo con el aislamiento añadido
+fn set_insulation(
+    model: &mut Model,
+    name: &str,
+    st: &ConsState,
+    candidates: &[Candidate],
+    step: f32,
+) {
+    if let Some(cons) = model.wallcons.iter_mut().find(|c| c.name == name) {
+        cons.r_intrinsic = st.r_intrinsic + st.r_added(candidates, step);
+        cons.thickness = st.thickness + st.steps as f32 * step;
+    }
+}
@@ -0,0 +1,238 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Modificación paramétrica de construcciones (escenarios de mejora)
+//!
+//! Permite modificar las composiciones de capas de los opacos (añadir capas o cambiar su espesor)
+//! y el vidrio o el marco de las construcciones de huecos, usando los materiales y elementos de la
+//! base de datos BDL del proyecto y del catálogo de HULC (ver ctehexml::parse_with_catalog).
+//!
+//! Las construcciones modificadas conservan su nombre e id, de modo que el cambio afecta a todos
+//! los elementos que las usan. Cada escenario genera un nuevo modelo y un reporte de diferencias.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, format_err, Error};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    from_ctehexml::{wallcons_from_bdl_cons, windowcons_from_bdl_cons},
+    shading::gglshwi_for_device,
+    Model, ScenarioReport, ValueDelta,
+};
+use crate::{bdl, utils::fround2};
+
+/// Modificación de una construcción del modelo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ConsChange {
+    /// Añade una capa de material a una composición de opaco
+    /// La posición es el índice de la capa, empezando por la cara exterior.
+    /// Si no se indica se añade en la cara exterior.
+    AddLayer {
+        wallcons: String,
+        material: String,
+        thickness: f32,
+        position: Option<usize>,
+    },
+    /// Cambia el espesor de las capas de un material de una composición de opaco
+    SetLayerThickness {
+        wallcons: String,
+        material: String,
+        thickness: f32,
+    },
+    /// Sustituye el vidrio de una construcción de hueco
+    SetGlass { wincons: String, glass: String },
+    /// Sustituye el marco de una construcción de hueco
+    SetFrame { wincons: String, frame: String },
+}
+
+impl Model {
+    /// Aplica un conjunto de modificaciones de construcciones y devuelve el nuevo modelo y el reporte de diferencias
+    ///
+    /// db es la base de datos BDL con las composiciones, materiales, vidrios y marcos del proyecto y del catálogo.
+    /// La transmitancia de los huecos modificados se calcula siempre con el método de HULC, aunque el modelo
+    /// se haya generado con el de UNE-EN ISO 10077-1, ya que el modelo no conserva la geometría (ancho y alto)
+    /// de los huecos que necesita ese método.
+    pub fn apply_cons_changes(
+        &self,
+        db: &bdl::DB,
+        changes: &[ConsChange],
+    ) -> Result<(Model, ScenarioReport), Error> {
+        let mut bdl_wallcons: BTreeMap<String, bdl::WallCons> = BTreeMap::new();
+        let mut bdl_wincons: BTreeMap<String, bdl::WindowCons> = BTreeMap::new();
+
+        for change in changes {
+            match change {
+                ConsChange::AddLayer {
+                    wallcons,
+                    material,
+                    thickness,
+                    position,
+                } => {
+                    check_material(db, material)?;
+                    let cons = bdl_wallcons_mut(db, &mut bdl_wallcons, wallcons)?;
+                    let pos = position.unwrap_or(0).min(cons.material.len());
+                    cons.material.insert(pos, material.clone());
+                    cons.thickness.insert(pos, *thickness);
+                }
+                ConsChange::SetLayerThickness {
+                    wallcons,
+                    material,
+                    thickness,
+                } => {
+                    let cons = bdl_wallcons_mut(db, &mut bdl_wallcons, wallcons)?;
+                    let mut found = false;
+                    for (mat, thk) in cons.material.iter().zip(cons.thickness.iter_mut()) {
+                        if mat == material {
+                            *thk = *thickness;
+                            found = true;
+                        }
+                    }
+                    if !found {
+                        bail!(
+                            "La composición {} no tiene capas del material {}",
+                            wallcons,
+                            material
+                        );
+                    }
+                }
+                ConsChange::SetGlass { wincons, glass } => {
+                    let g_new = db
+                        .glasses
+                        .get(glass)
+                        .ok_or_else(|| format_err!("Vidrio no encontrado: {}", glass))?
+                        .g_gln;
+                    let cons = bdl_wincons_mut(db, &mut bdl_wincons, wincons)?;
+                    // Se mantiene la reducción del factor solar debida a la protección solar móvil
+                    // reescalando g_gl;sh;wi con el factor solar del nuevo vidrio
+                    if let Some(gglshwi) = cons.gglshwi {
+                        cons.gglshwi = match db.glasses.get(&cons.glass) {
+                            Some(old) if old.g_gln > 0.0 => {
+                                Some((gglshwi * g_new / old.g_gln).min(1.0))
+                            }
+                            _ => None,
+                        };
+                    }
+                    cons.glass = glass.clone();
+                }
+                ConsChange::SetFrame { wincons, frame } => {
+                    if !db.frames.contains_key(frame) {
+                        bail!("Marco no encontrado: {}", frame);
+                    }
+                    bdl_wincons_mut(db, &mut bdl_wincons, wincons)?.frame = frame.clone();
+                }
+            }
+        }
+
+        let mut model = self.clone();
+        let mut report = ScenarioReport::default();
+
+        for (name, cons) in &bdl_wallcons {
+            let newcons = wallcons_from_bdl_cons(cons, &db.materials)?;
+            for wc in model.wallcons.iter_mut().filter(|wc| &wc.name == name) {
+                report
+                    .wallcons
+                    .push(ValueDelta::new(name, wc.r_intrinsic, newcons.r_intrinsic));
+                *wc = super::WallCons {
+                    id: wc.id.clone(),
+                    ..newcons.clone()
+                };
+            }
+        }
+        for (name, cons) in &bdl_wincons {
+            let u = cons.u(&db.frames, &db.glasses)?;
+            let newcons = windowcons_from_bdl_cons(cons, db, u)?;
+            for wc in model.wincons.iter_mut().filter(|wc| &wc.name == name) {
+                report.wincons.push(ValueDelta::new(name, wc.u, newcons.u));
+                // Con dispositivo de sombra asignado, g_gl;sh;wi se recalcula para el nuevo vidrio
+                let gglshwi = match &wc.shading {
+                    Some(device) => fround2(gglshwi_for_device(cons, &db.glasses, device)?),
+                    None => newcons.gglshwi,
+                };
+                *wc = super::WindowCons {
+                    id: wc.id.clone(),
+                    shading: wc.shading.clone(),
+                    gglshwi,
+                    ..newcons.clone()
+                };
+            }
+        }
+
+        // Transmitancias de los opacos que cambian
+        let (u_before, u_after) = (self.u_values(), model.u_values());
+        for (before, after) in self.walls.iter().zip(model.walls.iter()) {
+            if let (Some(u0), Some(u1)) = (u_before.wall(before), u_after.wall(after)) {
+                if (u0 - u1).abs() > 0.001 {
+                    report.walls.push(ValueDelta::new(&after.name, u0, u1));
+                }
+            }
+        }
+
+        report.K = ValueDelta::new(
+            "K",
+            self.K_he2019_with(&u_before).K,
+            model.K_he2019_with(&u_after).K,
+        );
+        report.q_soljul = ValueDelta::new(
+            "q_soljul",
+            self.q_soljul_with(&u_before),
+            model.q_soljul_with(&u_after),
+        );
+        report.n50 = ValueDelta::new("n50", self.n50(), model.n50());
+        info!(
+            "Escenario: K {:.2} -> {:.2} W/m²K, q_sol;jul {:.2} -> {:.2} kWh/m².mes, n50 {:.2} -> {:.2} 1/h",
+            report.K.before,
+            report.K.after,
+            report.q_soljul.before,
+            report.q_soljul.after,
+            report.n50.before,
+            report.n50.after
+        );
+
+        Ok((model, report))
+    }
+}
+
+/// Comprueba que un material existe en la base de datos
+fn check_material(db: &bdl::DB, material: &str) -> Result<(), Error> {
+    if db.materials.contains_key(material) {
+        Ok(())
+    } else {
+        bail!("Material no encontrado: {}", material)
+    }
+}
+
+/// Composición de opaco a modificar, partiendo de la de la base de datos si aún no se ha modificado
+fn bdl_wallcons_mut<'a>(
+    db: &bdl::DB,
+    modified: &'a mut BTreeMap<String, bdl::WallCons>,
+    name: &str,
+) -> Result<&'a mut bdl::WallCons, Error> {
+    if !modified.contains_key(name) {
+        let cons = db
+            .wallcons
+            .get(name)
+            .ok_or_else(|| format_err!("Composición de opaco no encontrada: {}", name))?;
+        modified.insert(name.to_string(), cons.clone());
+    }
+    Ok(modified.get_mut(name).unwrap())
+}
+
+/// Construcción de hueco a modificar, partiendo de la de la base de datos si aún no se ha modificado
+fn bdl_wincons_mut<'a>(
+    db: &bdl::DB,
+    modified: &'a mut BTreeMap<String, bdl::WindowCons>,
+    name: &str,
+) -> Result<&'a mut bdl::WindowCons, Error> {
+    if !modified.contains_key(name) {
+        let cons = db
+            .windowcons
+            .get(name)
+            .ok_or_else(|| format_err!("Construcción de hueco no encontrada: {}", name))?;
+        modified.insert(name.to_string(), cons.clone());
+    }
+    Ok(modified.get_mut(name).unwrap())
+}
@@ -0,0 +1,109 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Radiación solar mensual sobre superficies de inclinación y orientación cualesquiera
+//!
+//! Se obtiene a partir de los datos tabulados (RADDATA) para la superficie horizontal y las
+//! 8 orientaciones verticales principales:
+//! - la radiación directa se reproyecta como I_dir(β, γ) = cos(β)·I_dir,HZ + sen(β)·I_dir,V(γ)
+//!   (descomposición de cos θ en sus componentes vertical y horizontal)
+//! - la radiación difusa usa un modelo de cielo isótropo calibrado con la tabla:
+//!   I_dif(β, γ) = A·(1 + cos β)/2 + B·(1 - cos β)/2, con A = I_dif,HZ y B = 2·I_dif,V(γ) - I_dif,HZ,
+//!   donde B incluye la radiación reflejada por el terreno y la anisotropía del cielo
+//! - los valores para superficies verticales se interpolan linealmente entre las orientaciones tabuladas
+
+use super::{common::SurfaceMonthlyRadiation, ClimateZone, Orientation, Tilt, RADDATA};
+use crate::utils::normalize;
+
+/// Radiación mensual sobre una superficie con la inclinación y orientación dadas
+///
+/// - tilt: inclinación respecto a la horizontal, con la superficie mirando hacia arriba (0), vertical (90) o hacia abajo (180)
+/// - azimuth: orientación según UNE-EN ISO 52016-1 (S=0, E=+90, W=-90)
+///
+/// Los factores de uso de las protecciones solares móviles se interpolan linealmente con la inclinación
+pub fn radiation_for_surface(
+    zone: ClimateZone,
+    tilt: f32,
+    azimuth: f32,
+) -> SurfaceMonthlyRadiation {
+    // Criterio de la tabla RADDATA: S=0, E=-90, W=+90
+    let gamma = normalize(-azimuth, -180.0, 180.0);
+    let hz = RADDATA
+        .iter()
+        .find(|r| r.zone == zone && r.orientation == Orientation::HZ)
+        .unwrap();
+    let vert = vertical_radiation(zone, gamma);
+
+    let beta = tilt.to_radians();
+    let (cosb, sinb) = (beta.cos(), beta.sin().max(0.0));
+    let w_v = (tilt / 90.0).clamp(0.0, 1.0);
+
+    let mut rad = SurfaceMonthlyRadiation {
+        zone,
+        orientation: orientation_for(tilt, azimuth),
+        beta: tilt,
+        gamma,
+        dir: [0.0; 12],
+        dif: [0.0; 12],
+        tot: [0.0; 12],
+        f_shwith200: [0.0; 12],
+        f_shwith300: [0.0; 12],
+        f_shwith500: [0.0; 12],
+    };
+    for m in 0..12 {
+        let dir = cosb.max(0.0) * hz.dir[m] + sinb * vert.dir[m];
+        let a = hz.dif[m];
+        let b = (2.0 * vert.dif[m] - hz.dif[m]).max(0.0);
+        let dif = a * (1.0 + cosb) / 2.0 + b * (1.0 - cosb) / 2.0;
+        rad.dir[m] = dir;
+        rad.dif[m] = dif;
+        rad.tot[m] = dir + dif;
+        rad.f_shwith200[m] = lerp(hz.f_shwith200[m], vert.f_shwith200[m], w_v);
+        rad.f_shwith300[m] = lerp(hz.f_shwith300[m], vert.f_shwith300[m], w_v);
+        rad.f_shwith500[m] = lerp(hz.f_shwith500[m], vert.f_shwith500[m], w_v);
+    }
+    rad
+}
+
+/// Orientación nominal de una superficie según su inclinación y azimut
+fn orientation_for(tilt: f32, azimuth: f32) -> Orientation {
+    match Tilt::from(tilt) {
+        Tilt::SIDE => Orientation::from(azimuth),
+        _ => Orientation::HZ,
+    }
+}
+
+/// Radiación sobre una superficie vertical, interpolando entre las orientaciones tabuladas
+/// gamma en el criterio de la tabla RADDATA (S=0, E=-90, W=+90)
+fn vertical_radiation(zone: ClimateZone, gamma: f32) -> SurfaceMonthlyRadiation {
+    // Superficies verticales ordenadas cada 45º desde el sur y en sentido S->W->N->E
+    let mut vertical = RADDATA
+        .iter()
+        .filter(|r| r.zone == zone && r.orientation != Orientation::HZ)
+        .map(|r| (normalize(r.gamma, 0.0, 360.0), r))
+        .collect::<Vec<_>>();
+    vertical.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let angle = normalize(gamma, 0.0, 360.0);
+    let i = ((angle / 45.0).floor() as usize).min(7);
+    let j = (i + 1) % 8;
+    let t = (angle - vertical[i].0) / 45.0;
+    let (a, b) = (vertical[i].1, vertical[j].1);
+
+    let mut rad = a.clone();
+    rad.gamma = gamma;
+    for m in 0..12 {
+        rad.dir[m] = lerp(a.dir[m], b.dir[m], t);
+        rad.dif[m] = lerp(a.dif[m], b.dif[m], t);
+        rad.tot[m] = lerp(a.tot[m], b.tot[m], t);
+        rad.f_shwith200[m] = lerp(a.f_shwith200[m], b.f_shwith200[m], t);
+        rad.f_shwith300[m] = lerp(a.f_shwith300[m], b.f_shwith300[m], t);
+        rad.f_shwith500[m] = lerp(a.f_shwith500[m], b.f_shwith500[m], t);
+    }
+    rad
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
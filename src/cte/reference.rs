@@ -0,0 +1,257 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Edificio de referencia del DB-HE
+//!
+//! Se mantiene la geometría, los espacios y la superficie de los huecos del edificio objeto
+//! y se sustituyen:
+//! - las construcciones de opacos de la envolvente térmica por otras con la transmitancia límite
+//!   (Tabla 3.1.1.a - HE1), conservando su espesor y sus propiedades superficiales
+//! - las construcciones de huecos por otras con la transmitancia límite, el factor solar
+//!   de referencia y la permeabilidad límite (Tabla 3.1.3.b - HE1), conservando su fracción de marco
+//! - las transmitancias térmicas lineales de los puentes térmicos por los valores de referencia
+//!   (REFERENCE_PSI), que pueden sustituirse o ampliarse
+
+use std::collections::{BTreeMap, HashSet};
+
+use log::{debug, info, warn};
+
+use super::{
+    limits::{c_100_lim, summer_zone, u_lim, ULimKind},
+    model_impl::{RSE, RSI_ASCENDENTE, RSI_DESCENDENTE, RSI_HORIZONTAL},
+    BoundaryType, Model, Tilt, UValues, Wall, WallCons, WindowCons,
+};
+use crate::utils::fround3;
+
+/// Factor solar de referencia del hueco sin protección solar activada, g_gl;wi (-)
+/// Corresponde a un doble acristalamiento con g_gl;n = 0.67 (g_gl;wi = 0.90 · g_gl;n)
+pub const REFERENCE_GGLWI: f32 = 0.60;
+
+/// Factor solar de referencia del hueco con protección solar activada, g_gl;sh;wi (-)
+/// En zonas de verano 3 y 4 se considera una protección solar móvil exterior
+pub fn reference_gglshwi(climate: &super::ClimateZone) -> f32 {
+    match summer_zone(climate) {
+        3 | 4 => 0.30,
+        _ => REFERENCE_GGLWI,
+    }
+}
+
+/// Transmitancias térmicas lineales de referencia por tipo de puente térmico de HULC (W/mK)
+///
+/// Valores del DA DB-HE/3 para soluciones con aislamiento continuo por el exterior. Los tipos
+/// HUECO_ y ESQUINA_CONVEXA se aplican también a sus variantes (ver psi_for_name)
+pub static REFERENCE_PSI: [(&str, f32); 7] = [
+    ("FRENTE_FORJADO", 0.15),
+    ("UNION_CUBIERTA", 0.20),
+    ("UNION_SOLERA_PAREDEXT", 0.15),
+    ("PILAR", 0.05),
+    ("HUECO_", 0.10),
+    ("ESQUINA_CONVEXA", 0.05),
+    ("ESQUINA_CONCAVA", 0.0),
+];
+
+/// Transmitancias térmicas lineales de referencia, con los valores de REFERENCE_PSI sustituidos
+/// o ampliados con los indicados en overrides (W/mK)
+pub fn reference_psi(overrides: &BTreeMap<String, f32>) -> BTreeMap<String, f32> {
+    let mut psi = REFERENCE_PSI
+        .iter()
+        .map(|(tbtype, psi)| (tbtype.to_string(), *psi))
+        .collect::<BTreeMap<_, _>>();
+    psi.extend(overrides.iter().map(|(tbtype, psi)| (tbtype.clone(), *psi)));
+    psi
+}
+
+/// Transmitancia térmica lineal de un puente térmico según su tipo (W/mK)
+///
+/// psi: valores por tipo de puente térmico, con los nombres con los que HULC define los puentes
+/// térmicos (p.e. FRENTE_FORJADO). Se usa el tipo más largo con el que comienza el nombre, de modo
+/// que un valor para HUECO_ se aplica a HUECO_VENTANA.
+/// Devuelve None para los tipos sin valor.
+pub fn psi_for_name(psi: &BTreeMap<String, f32>, name: &str) -> Option<f32> {
+    psi.iter()
+        .filter(|(tbtype, _)| name.starts_with(tbtype.as_str()))
+        .max_by_key(|(tbtype, _)| tbtype.len())
+        .map(|(_, psi)| *psi)
+}
+
+/// Resistencia intrínseca de una construcción que alcanza una transmitancia dada según su posición (m2K/W)
+///
+/// Solo es válida para elementos en contacto con el aire exterior, ya que en los elementos en
+/// contacto con el terreno o con espacios no habitables la transmitancia incluye el efecto del
+/// terreno o del espacio adyacente (ver Model::r_intrinsic_for_wall)
+fn r_intrinsic_for_u(u: f32, tilt: Tilt) -> f32 {
+    let rsi = match tilt {
+        Tilt::TOP => RSI_ASCENDENTE,
+        Tilt::BOTTOM => RSI_DESCENDENTE,
+        Tilt::SIDE => RSI_HORIZONTAL,
+    };
+    (1.0 / u - rsi - RSE).max(0.0)
+}
+
+/// Resistencia intrínseca máxima considerada en la búsqueda de la construcción de referencia (m2K/W)
+const R_INTRINSIC_MAX: f32 = 20.0;
+
+impl Model {
+    /// Resistencia intrínseca de la construcción de un opaco con la que este alcanza la transmitancia u (m2K/W)
+    ///
+    /// En los elementos en contacto con el terreno o con espacios no habitables se busca por bisección
+    /// el valor con el que la transmitancia del elemento, que incluye el efecto del terreno o del espacio
+    /// adyacente, es igual a u. Si no se alcanza ni sin resistencia intrínseca se devuelve 0.0.
+    fn r_intrinsic_for_wall(&self, wall: &Wall, u: f32, u_values: &UValues) -> f32 {
+        use BoundaryType::*;
+
+        if !matches!(wall.bounds, GROUND | INTERIOR) {
+            return r_intrinsic_for_u(u, Tilt::from(wall));
+        }
+        let u_for_r = |r: f32| self.u_for_wall_with_r(wall, r, &u_values.walls, &u_values.index);
+        let (mut r_min, mut r_max) = (0.0, R_INTRINSIC_MAX);
+        match (u_for_r(r_min), u_for_r(r_max)) {
+            (Some(u_0), _) if u_0 <= u => return 0.0,
+            (Some(_), Some(u_max)) if u_max > u => {
+                warn!(
+                    "{} no alcanza U={:.2} con R={:.1} m2K/W (U={:.2})",
+                    wall.name, u, R_INTRINSIC_MAX, u_max
+                );
+                return R_INTRINSIC_MAX;
+            }
+            (Some(_), Some(_)) => (),
+            _ => return 0.0,
+        }
+        // La transmitancia decrece con la resistencia intrínseca
+        for _ in 0..50 {
+            let r = 0.5 * (r_min + r_max);
+            match u_for_r(r) {
+                Some(u_r) if u_r > u => r_min = r,
+                _ => r_max = r,
+            }
+        }
+        0.5 * (r_min + r_max)
+    }
+
+    /// Genera el edificio de referencia del DB-HE para la zona climática del modelo
+    ///
+    /// psi: transmitancias térmicas lineales por tipo de puente térmico (W/mK) que sustituyen o amplían
+    /// las de referencia (ver REFERENCE_PSI y psi_for_name). Los puentes térmicos de tipos sin valor
+    /// mantienen la transmitancia del proyecto.
+    ///
+    /// No se considera el ensayo de permeabilidad (n50) del edificio objeto
+    pub fn reference_building(&self, psi: &BTreeMap<String, f32>) -> Model {
+        let climate = self.meta.climate;
+        let u_values = self.u_values();
+        let mut model = self.clone();
+        model.meta.name = format!("{} (referencia)", self.meta.name);
+        model.meta.n50_test_ach = None;
+
+        // Opacos: una construcción de referencia por construcción original, tipo de límite y posición
+        // y, en los elementos en contacto con el terreno o con otros espacios, por elemento
+        let mut wallcons: BTreeMap<(String, ULimKind, Tilt, Option<String>), WallCons> =
+            BTreeMap::new();
+        for (wall, original_wall) in model.walls.iter_mut().zip(self.walls.iter()) {
+            let kind = match self.u_lim_kind_with(original_wall, &u_values.index) {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let tilt = Tilt::from(original_wall);
+            let by_wall = match wall.bounds {
+                BoundaryType::GROUND | BoundaryType::INTERIOR => Some(wall.name.clone()),
+                _ => None,
+            };
+            let key = (wall.cons.clone(), kind, tilt, by_wall);
+            if !wallcons.contains_key(&key) {
+                let original = match u_values.index.get_wallcons(original_wall) {
+                    Some(cons) => cons,
+                    None => continue,
+                };
+                let u = u_lim(&climate, kind);
+                let name = match &key.3 {
+                    Some(wallname) => {
+                        format!("{}_REF_{:?}_{}_{}", original.name, kind, tilt, wallname)
+                    }
+                    None => format!("{}_REF_{:?}_{}", original.name, kind, tilt),
+                };
+                let mut cons = WallCons {
+                    id: String::new(),
+                    name,
+                    group: "Referencia".to_string(),
+                    r_intrinsic: fround3(self.r_intrinsic_for_wall(original_wall, u, &u_values)),
+                    y_12: fround3(original.decrement_factor * u),
+                    ..original.clone()
+                };
+                cons.id = self.named_id("WallCons", &cons.name);
+                debug!(
+                    "Construcción de referencia {} para {:?} con U_lim={:.2}",
+                    cons.name, kind, u
+                );
+                wallcons.insert(key.clone(), cons);
+            }
+            wall.cons = wallcons[&key].id.clone();
+        }
+
+        // Huecos de la envolvente: una construcción de referencia por construcción original
+        let envelope_windows = self
+            .windows_of_envelope_with(&u_values.index)
+            .map(|w| w.id.as_str())
+            .collect::<HashSet<_>>();
+        let mut wincons: BTreeMap<String, WindowCons> = BTreeMap::new();
+        for win in model
+            .windows
+            .iter_mut()
+            .filter(|w| envelope_windows.contains(w.id.as_str()))
+        {
+            if !wincons.contains_key(&win.cons) {
+                let original = match u_values.index.get_wincons(win) {
+                    Some(cons) => cons,
+                    None => continue,
+                };
+                let mut cons = WindowCons {
+                    id: String::new(),
+                    name: format!("{}_REF", original.name),
+                    group: "Referencia".to_string(),
+                    u: u_lim(&climate, ULimKind::UH),
+                    ff: original.ff,
+                    gglwi: REFERENCE_GGLWI,
+                    gglshwi: reference_gglshwi(&climate),
+                    infcoeff_100: c_100_lim(&climate),
+                    shading: None,
+                };
+                cons.id = self.named_id("WindowCons", &cons.name);
+                wincons.insert(win.cons.clone(), cons);
+            }
+            win.cons = wincons[&win.cons].id.clone();
+        }
+
+        // Puentes térmicos
+        let psi = reference_psi(psi);
+        for tb in model.thermal_bridges.iter_mut() {
+            match psi_for_name(&psi, &tb.name) {
+                Some(psi) => tb.psi = psi,
+                None => debug!(
+                    "Puente térmico {} sin valor de referencia, se mantiene psi={:.2}",
+                    tb.name, tb.psi
+                ),
+            }
+        }
+
+        // Se eliminan las construcciones que ya no se usan y se añaden las de referencia
+        let (walls, windows) = (&model.walls, &model.windows);
+        model
+            .wallcons
+            .retain(|c| walls.iter().any(|w| w.cons == c.id));
+        model.wallcons.extend(wallcons.into_values());
+        model
+            .wincons
+            .retain(|c| windows.iter().any(|w| w.cons == c.id));
+        model.wincons.extend(wincons.into_values());
+        // Los datos de comprobación de HULC no corresponden al edificio de referencia
+        model.extra = None;
+
+        info!(
+            "Edificio de referencia generado para la zona {}: {} construcciones de opacos, {} de huecos",
+            climate,
+            model.wallcons.len(),
+            model.wincons.len()
+        );
+        model
+    }
+}
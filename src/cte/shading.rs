@@ -0,0 +1,179 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Dispositivos de sombra móviles de los huecos (persianas, estores, toldos...)
+//! - UNE-EN 13363-1:2004+A1:2008 Dispositivos de protección solar combinados con acristalamientos.
+//!   Cálculo de la transmitancia solar y luminosa. Parte 1: Método simplificado
+//! - Factores mensuales de uso de las protecciones móviles (f_sh;with) del DA DB-HE/1
+
+use std::collections::HashMap;
+
+use anyhow::{format_err, Error};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    common::SurfaceMonthlyRadiation, radiation::radiation_for_surface, Model, Window, WindowCons,
+};
+use crate::{bdl, utils::fround2};
+
+/// Irradiancia de activación por defecto de las protecciones solares móviles (W/m2)
+pub const ACTIVATION_DEFAULT: f32 = 300.0;
+
+/// Posición del dispositivo de sombra respecto al acristalamiento
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShadingPosition {
+    /// Dispositivo exterior (persiana, toldo, celosía...)
+    EXTERIOR,
+    /// Dispositivo interior (estor, cortina, veneciana interior...)
+    INTERIOR,
+}
+
+/// Dispositivo de sombra móvil
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadingDevice {
+    /// Nombre
+    pub name: String,
+    /// Posición respecto al acristalamiento
+    pub position: ShadingPosition,
+    /// Transmitancia solar del dispositivo, τ_e [-]
+    pub tau_e: f32,
+    /// Reflectancia solar del dispositivo, ρ_e [-]
+    pub rho_e: f32,
+    /// Irradiancia sobre el hueco a partir de la que se activa el dispositivo (200, 300 o 500 W/m2)
+    pub activation: f32,
+}
+
+impl ShadingDevice {
+    /// Absortancia solar del dispositivo, α_e = 1 - τ_e - ρ_e [-]
+    pub fn alpha_e(&self) -> f32 {
+        1.0 - self.tau_e - self.rho_e
+    }
+
+    /// Factor solar del acristalamiento con el dispositivo de sombra, g_tot = g_gl+sh
+    /// UNE-EN 13363-1:2004+A1:2008, ecuaciones (1) y (2)
+    ///
+    /// g_gl es el factor solar del acristalamiento y u_g su transmitancia térmica (W/m2K)
+    pub fn g_gl_sh(&self, g_gl: f32, u_g: f32) -> f32 {
+        if !(0.0..=0.5).contains(&self.tau_e)
+            || !(0.1..=0.8).contains(&self.rho_e)
+            || !(0.8..=6.0).contains(&u_g)
+            || !(0.15..=0.85).contains(&g_gl)
+        {
+            warn!(
+                "Dispositivo de sombra {} fuera del rango de aplicación del método simplificado de la UNE-EN 13363-1 (tau_e={}, rho_e={}, U_g={}, g={})",
+                self.name, self.tau_e, self.rho_e, u_g, g_gl
+            );
+        }
+        let alpha_e = self.alpha_e();
+        match self.position {
+            ShadingPosition::EXTERIOR => {
+                let (g1, g2) = (5.0, 10.0);
+                let g = 1.0 / (1.0 / u_g + 1.0 / g1 + 1.0 / g2);
+                self.tau_e * g_gl + alpha_e * g / g2 + self.tau_e * (1.0 - g_gl) * g / g1
+            }
+            ShadingPosition::INTERIOR => {
+                let g2 = 30.0;
+                let g = 1.0 / (1.0 / u_g + 1.0 / g2);
+                g_gl * (1.0 - g_gl * self.rho_e - alpha_e * g / g2)
+            }
+        }
+    }
+}
+
+impl SurfaceMonthlyRadiation {
+    /// Factores mensuales de uso de las protecciones solares móviles para la irradiancia de activación dada
+    /// Se usa el nivel disponible (200, 300 o 500 W/m2) más próximo
+    pub fn f_shwith(&self, activation: f32) -> &[f32; 12] {
+        if activation < 250.0 {
+            &self.f_shwith200
+        } else if activation < 400.0 {
+            &self.f_shwith300
+        } else {
+            &self.f_shwith500
+        }
+    }
+}
+
+/// Factor solar mensual efectivo del hueco, considerando el uso de las protecciones solares móviles
+///
+/// g_eff = (1 - f_sh;with) · g_gl;wi + f_sh;with · g_gl;sh;wi
+pub fn monthly_g_eff(gglwi: f32, gglshwi: f32, f_shwith: &[f32; 12]) -> [f32; 12] {
+    let mut g_eff = [0.0; 12];
+    for (g, f) in g_eff.iter_mut().zip(f_shwith) {
+        *g = (1.0 - f) * gglwi + f * gglshwi;
+    }
+    g_eff
+}
+
+/// Factor solar con la protección solar activada de una construcción de hueco, g_gl;sh;wi
+///
+/// Se parte del factor solar a incidencia normal del vidrio y se aplica el mismo factor de
+/// incidencia no normal (0.90) que para g_gl;wi
+pub fn gglshwi_for_device(
+    cons: &bdl::WindowCons,
+    glassesdb: &HashMap<String, bdl::Glass>,
+    device: &ShadingDevice,
+) -> Result<f32, Error> {
+    let glass = glassesdb.get(&cons.glass).ok_or_else(|| {
+        format_err!(
+            "Vidrio {} de la construcción {} no encontrado",
+            cons.glass,
+            cons.name
+        )
+    })?;
+    Ok(0.90 * device.g_gl_sh(glass.g_gln, glass.conductivity))
+}
+
+impl Model {
+    /// Asigna un dispositivo de sombra móvil a una construcción de hueco y actualiza su g_gl;sh;wi
+    pub fn set_shading_device(
+        &mut self,
+        db: &bdl::DB,
+        wincons: &str,
+        device: ShadingDevice,
+    ) -> Result<(), Error> {
+        let cons = self
+            .wincons
+            .iter_mut()
+            .find(|c| c.id == wincons || c.name == wincons)
+            .ok_or_else(|| format_err!("Construcción de hueco {} no encontrada", wincons))?;
+        let bdlcons = db.windowcons.get(&cons.name).ok_or_else(|| {
+            format_err!(
+                "Construcción de hueco {} no encontrada en la base de datos",
+                cons.name
+            )
+        })?;
+        cons.gglshwi = fround2(gglshwi_for_device(bdlcons, &db.glasses, &device)?);
+        cons.shading = Some(device);
+        Ok(())
+    }
+
+    /// Factores solares mensuales efectivos de un hueco, según la radiación de su orientación y
+    /// el uso de las protecciones solares móviles
+    pub fn window_monthly_g_eff(&self, window: &Window) -> Option<[f32; 12]> {
+        let wall = self.get_windowwall(window)?;
+        let wincons = self.get_wincons(window)?;
+        let radiation = radiation_for_surface(self.meta.climate, wall.tilt, wall.azimuth);
+        Some(wincons_monthly_g_eff(wincons, &radiation))
+    }
+}
+
+/// Factores solares mensuales efectivos de una construcción de hueco, según la radiación de la
+/// superficie en la que se sitúa y el uso de las protecciones solares móviles
+pub(crate) fn wincons_monthly_g_eff(
+    wincons: &WindowCons,
+    radiation: &SurfaceMonthlyRadiation,
+) -> [f32; 12] {
+    let activation = wincons
+        .shading
+        .as_ref()
+        .map(|s| s.activation)
+        .unwrap_or(ACTIVATION_DEFAULT);
+    monthly_g_eff(
+        wincons.gglwi,
+        wincons.gglshwi,
+        radiation.f_shwith(activation),
+    )
+}
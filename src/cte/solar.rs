@@ -0,0 +1,87 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Ganancias solares mensuales a través de la envolvente térmica
+//! - UNE-EN ISO 13790:2011, apartado 11.3 (huecos y elementos opacos)
+//! - DA DB-HE/1 para los datos de radiación mensual, ajustados a la inclinación y orientación de cada elemento
+
+use log::debug;
+
+use super::{
+    model_impl::RSE, radiation::radiation_for_surface, shading::wincons_monthly_g_eff,
+    BoundaryType, Model, Orientation, SolarGainsDetail, UValues,
+};
+
+impl Model {
+    /// Ganancias solares mensuales a través de huecos y opacos de la envolvente (kWh/mes)
+    ///
+    /// - Huecos: Q_sol = F_sh;obst · g_eff · (1 - FF) · A · H_sol
+    /// - Opacos: Q_sol = α · R_se · U · A · H_sol (sin considerar la radiación hacia la bóveda celeste)
+    ///
+    /// El factor solar mensual de los huecos tiene en cuenta el uso de las protecciones solares móviles
+    pub fn monthly_solar_gains(&self) -> SolarGainsDetail {
+        self.monthly_solar_gains_with(&self.u_values())
+    }
+
+    /// monthly_solar_gains con las transmitancias térmicas ya calculadas (ver Model::u_values)
+    pub fn monthly_solar_gains_with(&self, u_values: &UValues) -> SolarGainsDetail {
+        let mut detail = SolarGainsDetail::default();
+
+        let mut add = |orientation: Orientation, space: &str, is_window: bool, q: [f32; 12]| {
+            let orient_gains = detail.by_orientation.entry(orientation).or_default();
+            let space_gains = detail.by_space.entry(space.to_string()).or_default();
+            let kind_gains = if is_window {
+                &mut detail.windows
+            } else {
+                &mut detail.walls
+            };
+            for m in 0..12 {
+                orient_gains[m] += q[m];
+                space_gains[m] += q[m];
+                kind_gains[m] += q[m];
+                detail.total[m] += q[m];
+            }
+        };
+
+        let index = &u_values.index;
+        for win in self.windows_of_envelope_with(index) {
+            let (wall, wincons) = match (index.get_windowwall(win), index.get_wincons(win)) {
+                (Some(wall), Some(wincons)) => (wall, wincons),
+                _ => continue,
+            };
+            let orientation = Orientation::from(wall);
+            let radiation = radiation_for_surface(self.meta.climate, wall.tilt, wall.azimuth);
+            let g_eff = wincons_monthly_g_eff(wincons, &radiation);
+            let h_sol = radiation.tot;
+            let mut q = [0.0; 12];
+            for m in 0..12 {
+                q[m] = win.fshobst * g_eff[m] * (1.0 - wincons.ff) * win.area * h_sol[m];
+            }
+            debug!(
+                "Ganancias solares de {}: orient {}, g_eff {:?}, Q_sol {:?}",
+                win.name, orientation, g_eff, q
+            );
+            add(orientation, &wall.space, true, q);
+        }
+
+        for wall in self
+            .walls_of_envelope_with(index)
+            .filter(|w| w.bounds == BoundaryType::EXTERIOR)
+        {
+            let (wallcons, u) = match (index.get_wallcons(wall), u_values.wall(wall)) {
+                (Some(wallcons), Some(u)) => (wallcons, u),
+                _ => continue,
+            };
+            let orientation = Orientation::from(wall);
+            let h_sol = radiation_for_surface(self.meta.climate, wall.tilt, wall.azimuth).tot;
+            let mut q = [0.0; 12];
+            for m in 0..12 {
+                q[m] = wallcons.absorptance * RSE * u * wall.area * h_sol[m];
+            }
+            add(orientation, &wall.space, false, q);
+        }
+
+        detail
+    }
+}
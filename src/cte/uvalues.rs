@@ -0,0 +1,83 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Transmitancias térmicas de los elementos del modelo, calculadas una sola vez
+//!
+//! La U de los elementos interiores en contacto con espacios no acondicionados depende de la de
+//! los elementos de esos espacios en contacto con el exterior o el terreno, por lo que los opacos
+//! se recorren en el orden ADIABATIC -> EXTERIOR, GROUND -> INTERIOR para reutilizar los valores ya
+//! calculados.
+//!
+//! Las búsquedas de elementos usan los índices del modelo (ModelIndex), que se conservan para su
+//! uso en otros cálculos.
+//!
+//! La estructura toma prestado el modelo, de modo que no puede usarse tras modificarlo y debe
+//! volver a generarse.
+
+use std::collections::BTreeMap;
+
+use super::{BoundaryType, Model, ModelIndex, Wall, Window};
+
+/// Transmitancias térmicas de opacos y huecos del modelo (W/m2K)
+///
+/// Los elementos mal definidos (sin construcción o sin espacio) no tienen valor
+#[derive(Debug, Clone, Default)]
+pub struct UValues<'a> {
+    /// U de los opacos, por id del opaco
+    pub walls: BTreeMap<&'a str, f32>,
+    /// U de los huecos, por id del hueco
+    pub windows: BTreeMap<&'a str, f32>,
+    /// Índices de los elementos del modelo
+    pub index: ModelIndex<'a>,
+}
+
+impl<'a> UValues<'a> {
+    /// Calcula las transmitancias térmicas de todos los elementos del modelo
+    pub fn new(model: &'a Model) -> Self {
+        use BoundaryType::*;
+
+        let index = model.index();
+        let mut walls = BTreeMap::new();
+        for bounds in &[
+            [ADIABATIC, ADIABATIC],
+            [EXTERIOR, GROUND],
+            [INTERIOR, INTERIOR],
+        ] {
+            for wall in model.walls.iter().filter(|w| bounds.contains(&w.bounds)) {
+                if let Some(u) = model.u_for_wall_with(wall, &walls, &index) {
+                    walls.insert(wall.id.as_str(), u);
+                }
+            }
+        }
+
+        let windows = model
+            .windows
+            .iter()
+            .filter_map(|win| Some((win.id.as_str(), index.get_wincons(win)?.u)))
+            .collect();
+
+        UValues {
+            walls,
+            windows,
+            index,
+        }
+    }
+
+    /// U de un opaco
+    pub fn wall(&self, wall: &Wall) -> Option<f32> {
+        self.walls.get(wall.id.as_str()).copied()
+    }
+
+    /// U de un hueco
+    pub fn window(&self, window: &Window) -> Option<f32> {
+        self.windows.get(window.id.as_str()).copied()
+    }
+}
+
+impl Model {
+    /// Transmitancias térmicas de todos los opacos y huecos del modelo
+    pub fn u_values(&self) -> UValues<'_> {
+        UValues::new(self)
+    }
+}
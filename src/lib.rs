@@ -6,11 +6,11 @@ pub mod cte;
 pub mod parsers;
 pub mod utils;
 
-use std::{convert::TryFrom, path::Path};
+use std::path::Path;
 
 use anyhow::{format_err, Error};
 
-use cte::{BoundaryType, ExtraData, Model};
+use cte::{BoundaryType, ExtraData, IdScheme, Model, WindowUMethod};
 use parsers::{bdl, ctehexml, kyg, tbl};
 use utils::fround2;
 
@@ -36,18 +36,42 @@ Publicado bajo licencia MIT
 }
 
 /// Recoge datos desde archivo .ctehexml y, si se indica, del archivo KyGananciasSolares.txt
+///
+/// u_method es el método de cálculo de la transmitancia térmica de los huecos e ids el de generación de los id
 pub fn collect_hulc_data<T: AsRef<Path>>(
     ctehexmlpath: Option<T>,
     kygpath: Option<T>,
     tblpath: Option<T>,
+    u_method: WindowUMethod,
+    ids: IdScheme,
 ) -> Result<Model, Error> {
     // Carga .ctehexml y BBDD HULC
     let ctehexmlpath = &ctehexmlpath.ok_or_else(|| {
         format_err!("No se ha podido localizar el archivo .ctehexml del proyecto")
     })?;
-    // Genera Model desde BDL
     let ctehexmldata = ctehexml::parse_with_catalog(&ctehexmlpath)?;
-    let mut ecdata = Model::try_from(&ctehexmldata)?;
+    let mut ecdata = Model::try_from_ctehexml(&ctehexmldata, u_method, ids)?;
+    // Espacio de nombres de los id propio del proyecto, que no cambia al renombrarlo
+    if ids == IdScheme::Named {
+        ecdata.set_id_namespace_from_path(ctehexmlpath);
+        ecdata.set_named_ids();
+    }
+    fix_ecdata_from_extra(&mut ecdata, kygpath, tblpath);
+    Ok(ecdata)
+}
+
+/// Recoge datos a partir de los datos ya interpretados del archivo .ctehexml y, si se indica, de los archivos KyGananciasSolares.txt y NewBDL_O.tbl
+///
+/// Al no conocerse la ruta del archivo, los id se basan en el nombre del proyecto (ver Model::id_namespace)
+pub fn collect_hulc_data_from_ctehexml<T: AsRef<Path>>(
+    ctehexmldata: &ctehexml::CtehexmlData,
+    kygpath: Option<T>,
+    tblpath: Option<T>,
+    u_method: WindowUMethod,
+    ids: IdScheme,
+) -> Result<Model, Error> {
+    // Genera Model desde BDL con los métodos de cálculo de U de huecos y de generación de id indicados
+    let mut ecdata = Model::try_from_ctehexml(ctehexmldata, u_method, ids)?;
     // Interpreta .kyg y añade datos que faltan con archivos adicionales
     fix_ecdata_from_extra(&mut ecdata, kygpath, tblpath);
     // Devuelve datos ampliados y corregidos (U, Fshobst)
@@ -60,6 +84,8 @@ pub fn fix_ecdata_from_extra<T: AsRef<Path>>(
     kygpath: Option<T>,
     tblpath: Option<T>,
 ) {
+    let u_values = ecdata.u_values();
+    let btrs = ecdata.btr_of_spaces(&u_values);
     let mut extra = ecdata
         .walls
         .iter()
@@ -76,7 +102,9 @@ pub fn fix_ecdata_from_extra<T: AsRef<Path>>(
             tilt: w.tilt.into(),
             cons: w.cons.clone(),
             u: 0.0,
-            computed_u: fround2(ecdata.u_for_wall(w).unwrap_or(0.0)),
+            computed_u: fround2(u_values.wall(w).unwrap_or(0.0)),
+            btr: None,
+            computed_btr: fround2(ecdata.btr_for_wall_from(w, &u_values, &btrs).unwrap_or(0.0)),
         })
         .collect::<Vec<_>>();
 
@@ -95,6 +123,7 @@ pub fn fix_ecdata_from_extra<T: AsRef<Path>>(
             let kygwall = kygdata.walls.get(wallname);
             if let Some(kw) = kygwall {
                 e.u = fround2(kw.u);
+                e.btr = Some(fround2(kw.btrx));
             }
         }
 
@@ -70,6 +70,61 @@ impl WindowCons {
             (1.0 + deltau / 100.0) * (frameu * self.framefrac + glassu * (1.0 - self.framefrac));
         Ok(u)
     }
+
+    /// Cálculo de U de un hueco de dimensiones width x height (m) según UNE-EN ISO 10077-1:2017, ecuación (1)
+    ///
+    /// U_w = (A_g·U_g + A_f·U_f + l_g·ψ_g) / (A_g + A_f)
+    ///
+    /// El área de marco se obtiene con el ancho del marco (Frame.width) y psi_g es la transmitancia
+    /// térmica lineal del intercalario del acristalamiento (W/mK).
+    /// Si el marco no define su ancho se usa la fracción de marco de la construcción y el perímetro del hueco.
+    /// No se aplica el incremento porcentual de U (deltau), ya que el efecto de los intercalarios se incluye con psi_g.
+    pub fn u_iso10077(
+        &self,
+        framesdb: &HashMap<String, Frame>,
+        glassesdb: &HashMap<String, Glass>,
+        width: f32,
+        height: f32,
+        psi_g: f32,
+    ) -> Result<f32, Error> {
+        let glass = glassesdb.get(&self.glass).ok_or_else(|| {
+            format_err!(
+                "Vidrio {} de la construcción {} no encontrado",
+                self.glass,
+                self.name
+            )
+        })?;
+        let frame = framesdb.get(&self.frame).ok_or_else(|| {
+            format_err!(
+                "Marco {} de la construcción {} no encontrado",
+                self.frame,
+                self.name
+            )
+        })?;
+        let area = width * height;
+        if area <= 0.0 {
+            return Err(format_err!(
+                "Dimensiones de hueco incorrectas para la construcción {}: {} x {}",
+                self.name,
+                width,
+                height
+            ));
+        }
+        let (a_g, l_g) = if frame.width > 0.0 {
+            let glass_w = (width - 2.0 * frame.width).max(0.0);
+            let glass_h = (height - 2.0 * frame.width).max(0.0);
+            if glass_w > 0.0 && glass_h > 0.0 {
+                (glass_w * glass_h, 2.0 * (glass_w + glass_h))
+            } else {
+                (0.0, 0.0)
+            }
+        } else {
+            ((1.0 - self.framefrac) * area, 2.0 * (width + height))
+        };
+        let a_f = area - a_g;
+        let u = (a_g * glass.conductivity + a_f * frame.conductivity + l_g * psi_g) / area;
+        Ok(u)
+    }
 }
 
 impl TryFrom<BdlBlock> for WindowCons {
@@ -108,9 +108,8 @@ impl Space {
 
         // Muros exteriores (verticales)
         let vertical_walls_for_space = db
-            .walls
-            .iter()
-            .filter(|w| w.space == self.name && w.position() == Tilt::SIDE);
+            .walls_of_space(&self.name)
+            .filter(|w| w.position() == Tilt::SIDE);
 
         // Area bruta total de muros y área bruta de muros exteriores
         let (total_vwalls_area, exterior_vwalls_area) = vertical_walls_for_space
@@ -123,7 +122,7 @@ impl Space {
                     INTERIOR => {
                         w.nextto
                             .as_deref()
-                            .and_then(|nxts| db.spaces.iter().find(|s| s.name == nxts))
+                            .and_then(|nxts| db.get_space(nxts))
                             .and_then(|nextspace| {
                                 if nextspace.stype != "CONDITIONED" {
                                     // tenemos en cuenta el contacto de espacios acondicionados con otros tipos
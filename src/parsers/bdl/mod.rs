@@ -52,6 +52,43 @@ pub struct Data {
     pub systemconds: HashMap<String, BdlBlock>,
     /// Horarios
     pub schedules: HashMap<String, BdlBlock>,
+    /// Índices de los elementos por nombre (ver Data::reindex)
+    index: DataIndex,
+}
+
+/// Índices de posición de los elementos de Data por nombre
+///
+/// Se generan al final de la lectura. Si se modifican las listas de elementos deben regenerarse
+/// con Data::reindex, aunque las búsquedas comprueban el nombre y recorren la lista si no coincide.
+#[derive(Debug, Clone, Default)]
+struct DataIndex {
+    /// Posición de los espacios, por nombre
+    spaces: HashMap<String, usize>,
+    /// Posición de los opacos, por nombre
+    walls: HashMap<String, usize>,
+    /// Posición de los huecos, por nombre
+    windows: HashMap<String, usize>,
+}
+
+/// Posición de los elementos por nombre. Con nombres repetidos se usa el primer elemento
+fn name_positions<'a>(names: impl Iterator<Item = &'a String>) -> HashMap<String, usize> {
+    let mut pos = HashMap::new();
+    for (i, name) in names.enumerate() {
+        pos.entry(name.clone()).or_insert(i);
+    }
+    pos
+}
+
+/// Elemento en la posición indicada por el índice, si su nombre coincide, o búsqueda en la lista
+fn find_indexed<'a, T>(
+    elements: &'a [T],
+    pos: Option<&usize>,
+    name: &str,
+    get_name: impl Fn(&T) -> &str,
+) -> Option<&'a T> {
+    pos.and_then(|&i| elements.get(i))
+        .filter(|e| get_name(e) == name)
+        .or_else(|| elements.iter().find(|e| get_name(e) == name))
 }
 
 impl Data {
@@ -262,21 +299,49 @@ impl Data {
             };
         }
 
+        bdldata.reindex();
+
         Ok(bdldata)
     }
 
+    /// Regenera los índices de los elementos por nombre
+    pub fn reindex(&mut self) {
+        self.index = DataIndex {
+            spaces: name_positions(self.spaces.iter().map(|s| &s.name)),
+            walls: name_positions(self.walls.iter().map(|w| &w.name)),
+            windows: name_positions(self.windows.iter().map(|w| &w.name)),
+        };
+    }
+
     /// Localiza hueco
     pub fn get_window<T: AsRef<str>>(&self, name: T) -> Option<&Window> {
-        self.windows.iter().find(|w| w.name == name.as_ref())
+        let name = name.as_ref();
+        find_indexed(&self.windows, self.index.windows.get(name), name, |w| {
+            w.name.as_str()
+        })
     }
 
     /// Localiza muro
     pub fn get_wall<T: AsRef<str>>(&self, name: T) -> Option<&Wall> {
-        self.walls.iter().find(|w| w.name == name.as_ref())
+        let name = name.as_ref();
+        find_indexed(&self.walls, self.index.walls.get(name), name, |w| {
+            w.name.as_str()
+        })
     }
 
     /// Localiza espacio
     pub fn get_space<T: AsRef<str>>(&self, name: T) -> Option<&Space> {
-        self.spaces.iter().find(|w| w.name == name.as_ref())
+        let name = name.as_ref();
+        find_indexed(&self.spaces, self.index.spaces.get(name), name, |s| {
+            s.name.as_str()
+        })
+    }
+
+    /// Iterador de los opacos de un espacio
+    ///
+    /// Recorre la lista de opacos, ya que un índice de posiciones no detecta los opacos añadidos
+    /// o reasignados a otro espacio tras generarlo
+    pub fn walls_of_space<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Wall> + 'a {
+        self.walls.iter().filter(move |w| w.space == name)
     }
 }
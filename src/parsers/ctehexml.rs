@@ -10,7 +10,7 @@ use std::path::{Path, PathBuf};
 use anyhow::{format_err, Error};
 use flate2::read::GzDecoder;
 
-use crate::bdl::Data;
+use crate::bdl::{Data, DB};
 use crate::utils::{find_file_in_basedir, read_file};
 
 /// Datos del archivo .ctehexml
@@ -60,12 +60,22 @@ pub struct DatosGenerales {
     pub num_viviendas_bloque: i32,
     /// Caudal de ventilación l/s
     pub valor_impulsion_aire: f32,
-    /// Zona climática (alfa1, A1, ...). Este valor no indica si el clima es canario o no
+    /// Zona climática (alfa1, A1, ...). Este valor no indica si el clima es canario o no (ver comunidad)
     pub zona_climatica: String,
     /// Zona climática según archivo climático (alfa1c, A1, A1c, ...)
     pub archivo_climatico: String,
+    /// Comunidad autónoma
+    pub comunidad: String,
+    /// Provincia
+    pub provincia: String,
+    /// Localidad
+    pub localidad: String,
+    /// Altitud de la localidad (m)
+    pub altitud: Option<f32>,
     /// Valor del ensayo de permeabilidad (solo residencial)
     pub valor_n50_medido: Option<f32>,
+    /// Clase de higrometría de los espacios (1 a 5)
+    pub clase_higrometria: u8,
     /// Contenido del bloque en texto, sin parsear
     pub bloque_raw: String,
 }
@@ -117,7 +127,7 @@ pub fn parse<T: AsRef<Path>>(path: T) -> Result<CtehexmlData, Error> {
         _ => None,
     };
 
-    // XXX: No usamos zona_climatica puesto que no diferencia climas canarios
+    // zona_climatica no diferencia los climas canarios. Ver cte::climatezone_from_hulc
     let zona_climatica = get_tag_as_str(&datos_generales, "zonaClimatica").to_string();
     let archivo_climatico =
         get_tag_as_str(&datos_generales, "pathArchivoMeteorologicoSeleccionado")
@@ -143,7 +153,19 @@ pub fn parse<T: AsRef<Path>>(path: T) -> Result<CtehexmlData, Error> {
         valor_impulsion_aire: get_tag_as_f32(&datos_generales, "valorImpulsionAire")?,
         zona_climatica,
         archivo_climatico,
+        comunidad: get_tag_as_str(&datos_generales, "comunidad").to_string(),
+        provincia: get_tag_as_str(&datos_generales, "provincia").to_string(),
+        // En algunas versiones solo se guarda la localidad en los datos del proyecto
+        localidad: match get_tag_as_str(&datos_generales, "poblacion") {
+            "" => get_tag_as_str(&datos_generales, "locPro").to_string(),
+            poblacion => poblacion.to_string(),
+        },
+        altitud: get_tag_as_f32(&datos_generales, "altitud").ok(),
         valor_n50_medido,
+        // Los archivos antiguos pueden no definir la clase de higrometría. Usamos la clase 3 por defecto
+        clase_higrometria: get_tag_as_str(&datos_generales, "claseHigrometria")
+            .parse::<u8>()
+            .unwrap_or(3),
         bloque_raw: datos_generales.text().unwrap_or("").trim().to_string(),
     };
 
@@ -175,16 +197,21 @@ pub fn parse<T: AsRef<Path>>(path: T) -> Result<CtehexmlData, Error> {
 
 static LIDERCATSTRZ: &[u8] = include_bytes!("BDCatalogo.bdc.utf8.gz");
 
+/// Carga la BBDD por defecto de HULC (catálogo de materiales y construcciones)
+pub fn catalog() -> Result<DB, Error> {
+    let mut gz = GzDecoder::new(LIDERCATSTRZ);
+    let mut dbstring = String::new();
+    gz.read_to_string(&mut dbstring)?;
+    Ok(Data::new(&dbstring)?.db)
+}
+
 /// Carga archivo .ctehexml y extiende con BBDD por defecto de HULC
 pub fn parse_with_catalog<T: AsRef<Path>>(path: T) -> Result<CtehexmlData, Error> {
     // Carga archivo .ctehexml
     let mut ctehexmldata = parse(path.as_ref())?;
     let mut db = ctehexmldata.bdldata.db;
     // Carga datos del catálogo comprimido
-    let mut gz = GzDecoder::new(LIDERCATSTRZ);
-    let mut dbstring = String::new();
-    gz.read_to_string(&mut dbstring)?;
-    let catdb = Data::new(&dbstring)?.db;
+    let catdb = catalog()?;
     db.materials.extend(catdb.materials);
     db.wallcons.extend(catdb.wallcons);
     db.windowcons.extend(catdb.windowcons);
@@ -0,0 +1,184 @@
+// Copyright (c) 2018-2020 Rafael Villar Burke <pachi@ietcc.csic.es>
+// Distributed under the MIT License
+// (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
+
+//! Funciones de interpretación de archivos climáticos horarios .met (zonaXX.met) del CTE
+//!
+//! Formato:
+//!
+//! ```text
+//!     zonaD3.met
+//!     Latitud [ESPACIO] Longitud [ESPACIO] Altitud [ESPACIO] Longitud del meridiano de referencia
+//!     Mes [ESPACIO] Día [ESPACIO] Hora [ESPACIO] Temperatura seca (ºC) [ESPACIO] Temperatura de cielo (ºC) [ESPACIO] Irradiancia directa sobre superficie horizontal (W/m2) [ESPACIO] Irradiancia difusa sobre superficie horizontal (W/m2) [ESPACIO] Humedad específica (kg/kg) [ESPACIO] Humedad relativa (%) [ESPACIO] Velocidad del viento (m/s) [ESPACIO] Dirección del viento (grados respecto al norte, E+) [ESPACIO] Azimut solar (grados, S=0, E-, W+) [ESPACIO] Cénit solar (grados)
+//!     #NOTA: La línea 3 se repite para cada una de las 8760 horas del año
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, format_err, Context, Error};
+
+use crate::utils::{find_file_in_basedir, read_latin1_file};
+
+/// Localiza el archivo climático de una zona (zonaXX.met) en el directorio basedir
+///
+/// climate: zona climática, tal como se obtiene del archivo climático de los datos generales del .ctehexml (p.e. D3)
+pub fn find_met<T: AsRef<str>>(basedir: T, climate: &str) -> Result<Option<PathBuf>, Error> {
+    find_file_in_basedir(basedir, &format!("zona{}.met", climate))
+}
+
+/// Número de horas de un año climático
+pub const HOURS_IN_YEAR: usize = 8760;
+
+/// Datos climáticos de una hora
+#[derive(Debug, Copy, Clone, Default)]
+pub struct MetHour {
+    /// Mes [1-12]
+    pub month: u32,
+    /// Día [1-31]
+    pub day: u32,
+    /// Hora [1-24]
+    pub hour: u32,
+    /// Temperatura seca del aire (ºC)
+    pub temp: f32,
+    /// Temperatura efectiva del cielo (ºC)
+    pub temp_sky: f32,
+    /// Irradiancia directa sobre superficie horizontal (W/m2)
+    pub rad_dir_h: f32,
+    /// Irradiancia difusa sobre superficie horizontal (W/m2)
+    pub rad_dif_h: f32,
+    /// Humedad específica (kg/kg)
+    pub abs_humidity: f32,
+    /// Humedad relativa (%)
+    pub rel_humidity: f32,
+    /// Velocidad del viento (m/s)
+    pub wind_speed: f32,
+    /// Dirección del viento (grados respecto al norte, E+)
+    pub wind_dir: f32,
+    /// Azimut solar (grados, S=0, E-, W+)
+    pub sun_azimuth: f32,
+    /// Cénit solar (grados)
+    pub sun_zenith: f32,
+}
+
+impl MetHour {
+    /// Irradiancia global sobre superficie horizontal (W/m2)
+    pub fn rad_glob_h(&self) -> f32 {
+        self.rad_dir_h + self.rad_dif_h
+    }
+}
+
+impl FromStr for MetHour {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let data: Vec<&str> = s.split_whitespace().collect();
+        if data.len() != 13 {
+            return Err(format_err!(
+                "Formato incorrecto de los datos horarios: {}",
+                s
+            ));
+        }
+        Ok(MetHour {
+            month: data[0].parse()?,
+            day: data[1].parse()?,
+            hour: data[2].parse()?,
+            temp: data[3].parse()?,
+            temp_sky: data[4].parse()?,
+            rad_dir_h: data[5].parse()?,
+            rad_dif_h: data[6].parse()?,
+            abs_humidity: data[7].parse()?,
+            rel_humidity: data[8].parse()?,
+            wind_speed: data[9].parse()?,
+            wind_dir: data[10].parse()?,
+            sun_azimuth: data[11].parse()?,
+            sun_zenith: data[12].parse()?,
+        })
+    }
+}
+
+/// Datos de un archivo climático .met
+#[derive(Debug, Clone, Default)]
+pub struct MetData {
+    /// Nombre del archivo climático (p.e. zonaD3.met)
+    pub name: String,
+    /// Latitud (grados)
+    pub latitude: f32,
+    /// Longitud (grados)
+    pub longitude: f32,
+    /// Altitud (m)
+    pub altitude: f32,
+    /// Longitud del meridiano de referencia (grados)
+    pub meridian: f32,
+    /// Datos horarios (8760 valores)
+    pub hours: Vec<MetHour>,
+}
+
+impl MetData {
+    /// Iterador de los datos horarios de un mes [1-12]
+    pub fn hours_of_month(&self, month: u32) -> impl Iterator<Item = &MetHour> {
+        self.hours.iter().filter(move |h| h.month == month)
+    }
+}
+
+/// Interpreta archivo climático .met
+///
+/// path: ruta del archivo .met
+pub fn parse<T: AsRef<Path>>(path: T) -> Result<MetData, Error> {
+    let utf8buf = read_latin1_file(path.as_ref())?;
+    parse_str(&utf8buf)
+}
+
+/// Interpreta los datos de un archivo climático .met
+pub fn parse_str(data: &str) -> Result<MetData, Error> {
+    let mut lines = data.lines().filter(|l| !l.trim().is_empty());
+
+    let name = lines
+        .next()
+        .ok_or_else(|| format_err!("Error al leer el archivo .met: archivo vacío"))?
+        .trim()
+        .to_string();
+
+    let location = lines
+        .next()
+        .ok_or_else(|| {
+            format_err!(
+                "Error al leer el archivo .met: no se ha localizado la línea de localización"
+            )
+        })?
+        .split_whitespace()
+        .map(|s| {
+            s.parse::<f32>().with_context(|| {
+                "Error al leer el archivo .met: formato incorrecto de la línea de localización"
+            })
+        })
+        .collect::<Result<Vec<f32>, _>>()?;
+    if location.len() < 4 {
+        bail!("Error al leer el archivo .met: formato incorrecto de la línea de localización")
+    };
+
+    let hours = lines
+        .map(|l| {
+            l.parse::<MetHour>()
+                .with_context(|| "Error al leer el archivo .met")
+        })
+        .collect::<Result<Vec<MetHour>, _>>()?;
+    if hours.len() != HOURS_IN_YEAR {
+        bail!(
+            "Error al leer el archivo .met: se esperaban {} datos horarios y se han encontrado {}",
+            HOURS_IN_YEAR,
+            hours.len()
+        )
+    }
+
+    Ok(MetData {
+        name,
+        latitude: location[0],
+        longitude: location[1],
+        altitude: location[2],
+        meridian: location[3],
+        hours,
+    })
+}
@@ -2,9 +2,10 @@
 // Distributed under the MIT License
 // (See acoompanying LICENSE file or a copy at http://opensource.org/licenses/MIT)
 
-//! Parsers de formatos de HULC: bdl, ctehexml, kyg, tbl
+//! Parsers de formatos de HULC: bdl, ctehexml, kyg, tbl, met
 
 pub mod bdl;
 pub mod ctehexml;
 pub mod kyg;
+pub mod met;
 pub mod tbl;
@@ -13,6 +13,7 @@ use encoding::all::ISO_8859_1;
 use encoding::{DecoderTrap, Encoding};
 
 use glob::glob;
+use uuid::Uuid;
 
 /// Localiza archivo que sigue el patrón pat en el directorio dir
 /// Falla si hay algún error en el patrón
@@ -146,7 +147,9 @@ pub fn angle_name(angle: f32) -> String {
 
 /// Calcula UUID a partir de hash MD5 del objeto
 ///
-/// Este no es un método muy robusto pero da valores estables para los mismos objetos
+/// Este no es un método muy robusto pero da valores estables para los mismos objetos.
+/// Cualquier cambio en los datos o en su formato Debug cambia el id.
+/// Se mantiene por compatibilidad (IdScheme::Legacy). Ver uuid_from_name
 pub fn uuid_from_obj(obj: &impl std::fmt::Debug) -> String {
     let h = format!("{:x}", md5::compute(format!("{:?}", obj).as_bytes()));
     format!(
@@ -158,3 +161,15 @@ pub fn uuid_from_obj(obj: &impl std::fmt::Debug) -> String {
         &h[20..32]
     )
 }
+
+/// Espacio de nombres UUID de la aplicación
+///
+/// UUIDv5 del espacio de nombres URL para https://github.com/pachi/hulc2envolventecte
+pub const UUID_NAMESPACE: Uuid = Uuid::from_u128(0x85268f22_0929_56ef_899c_4d11d03ef880);
+
+/// Calcula UUID de un elemento a partir del espacio de nombres del proyecto, el tipo de elemento y su nombre
+///
+/// El id es estable mientras no cambie el nombre del elemento
+pub fn uuid_from_name(namespace: &Uuid, kind: &str, name: &str) -> String {
+    Uuid::new_v5(namespace, format!("{}:{}", kind, name).as_bytes()).to_string()
+}